@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::challonge::{Entrant, Match};
+use crate::dispatch::BracketGraph;
+use crate::server::ReportedResult;
+use crate::steamid::{self, SteamId};
+
+pub const ARCHIVE_DIR: &str = "archive";
+// how many placements the game servers get told about
+pub const TOP_PLACEMENTS: usize = 8;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Placement {
+    pub rank: u64,
+    #[serde(
+        default,
+        deserialize_with = "steamid::optional",
+        serialize_with = "steamid::serialize_optional"
+    )]
+    pub steam_id: Option<SteamId>,
+    pub name: String,
+}
+
+/// Final standings, best first. Challonge's final ranks once the tournament is
+/// finalized, otherwise our own guess: the winner of the final, its loser, then
+/// everyone else by wins.
+pub fn placements(matches: &[Match], entrants: &HashMap<u64, Entrant>) -> Vec<Placement> {
+    let mut ranked: Vec<(u64, &Entrant)> = entrants
+        .values()
+        .filter_map(|e| Some((e.final_rank?, e)))
+        .collect();
+
+    if ranked.is_empty() {
+        let mut record: HashMap<u64, (u64, u64)> =
+            entrants.keys().map(|&pid| (pid, (0, 0))).collect();
+        for m in matches {
+            let (Some(winner), Some(p1), Some(p2)) = (m.winner_id, m.player1_id, m.player2_id)
+            else {
+                continue;
+            };
+            let loser = if winner == p1 { p2 } else { p1 };
+            record.entry(winner).or_default().0 += 1;
+            record.entry(loser).or_default().1 += 1;
+        }
+        // the final feeds nothing, the losers bracket can run more rounds than the
+        // winners so round numbers don't tell. A grand final reset sits one further down.
+        let graph = BracketGraph::new(matches);
+        let last = matches
+            .iter()
+            .filter(|m| m.winner_id.is_some())
+            .min_by_key(|m| {
+                (
+                    graph.height(m.id),
+                    std::cmp::Reverse(m.suggested_play_order.unwrap_or(0)),
+                )
+            });
+        let finalists: Vec<u64> = last
+            .and_then(|m| {
+                let winner = m.winner_id?;
+                let loser = if m.player1_id == Some(winner) {
+                    m.player2_id?
+                } else {
+                    m.player1_id?
+                };
+                Some(vec![winner, loser])
+            })
+            .unwrap_or_default();
+
+        let mut pids: Vec<u64> = entrants.keys().copied().collect();
+        pids.sort_by_key(|pid| {
+            let (wins, losses) = record.get(pid).copied().unwrap_or_default();
+            let finalist = finalists
+                .iter()
+                .position(|f| f == pid)
+                .unwrap_or(finalists.len());
+            (finalist, std::cmp::Reverse(wins), losses)
+        });
+        ranked = pids
+            .into_iter()
+            .enumerate()
+            .map(|(i, pid)| (i as u64 + 1, &entrants[&pid]))
+            .collect();
+    }
+
+    ranked.sort_by_key(|(rank, e)| (*rank, e.name.clone()));
+    ranked
+        .into_iter()
+        .map(|(rank, e)| Placement {
+            rank,
+            steam_id: e.steam_id,
+            name: e.name.clone(),
+        })
+        .collect()
+}
+
+/// What's kept of a finished tournament, one file per tournament in ARCHIVE_DIR.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Archive<'a> {
+    pub name: &'a str,
+    pub url: &'a str,
+    pub finished_at: DateTime<Utc>,
+    pub placements: &'a [Placement],
+    pub results: &'a [ReportedResult],
+}
+
+impl Archive<'_> {
+    pub fn save(&self) {
+        let path = format!("{}/{}.json", ARCHIVE_DIR, self.url);
+        let text = serde_json::to_string_pretty(self).unwrap();
+        if let Err(e) =
+            std::fs::create_dir_all(ARCHIVE_DIR).and_then(|_| std::fs::write(&path, text))
+        {
+            println!("could not write {}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challonge::testing::entrants;
+
+    fn ranked(final_ranks: &[Option<u64>]) -> HashMap<u64, Entrant> {
+        let mut entrants = entrants(final_ranks.len() as u64);
+        for (pid, &final_rank) in (1..).zip(final_ranks) {
+            entrants.get_mut(&pid).unwrap().final_rank = final_rank;
+        }
+        entrants
+    }
+
+    fn names(placements: &[Placement]) -> Vec<&str> {
+        placements.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    // four players double elimination, numbered so the losers final has the highest
+    // round: p1 wins winners, then loses the grand final and the reset to p2 coming
+    // from losers
+    fn double_elim() -> Vec<Match> {
+        vec![
+            Match::between(1, 1, 1, 4, Some(1)),
+            Match::between(2, 1, 2, 3, Some(3)),
+            Match::between(3, 2, 1, 3, Some(1)).after(1, 2),
+            Match::between(4, -1, 4, 2, Some(2)).after(1, 2),
+            Match::between(5, -2, 3, 2, Some(2)).after(3, 4),
+            Match::between(6, -4, 3, 2, Some(2)).after(5, 5),
+            Match::between(7, 3, 1, 2, Some(2)).after(3, 6),
+            Match::between(8, 3, 1, 2, Some(2)).after(7, 7),
+        ]
+    }
+
+    #[test]
+    fn challonge_ranks_win() {
+        let placements = placements(&[], &ranked(&[Some(2), Some(1), Some(3), Some(3)]));
+        assert_eq!(names(&placements), vec!["p2", "p1", "p3", "p4"]);
+        assert_eq!(placements[3].rank, 3);
+    }
+
+    #[test]
+    fn guessed_from_the_grand_final() {
+        let placements = placements(&double_elim(), &ranked(&[None; 4]));
+        assert_eq!(&names(&placements)[..2], &["p2", "p1"]);
+        assert_eq!(placements[0].rank, 1);
+    }
+
+    #[test]
+    fn guessed_without_a_reset() {
+        let mut matches = double_elim();
+        matches.pop();
+        matches[6].winner_id = Some(1);
+        let placements = placements(&matches, &ranked(&[None; 4]));
+        assert_eq!(&names(&placements)[..2], &["p1", "p2"]);
+    }
+
+    #[test]
+    fn the_rest_go_by_wins() {
+        let placements = placements(&double_elim(), &ranked(&[None; 4]));
+        assert_eq!(&names(&placements)[2..], &["p3", "p4"]);
+    }
+
+    #[test]
+    fn nothing_played() {
+        let placements = placements(&[], &ranked(&[None; 2]));
+        assert_eq!(placements.len(), 2);
+        assert!(placements.iter().all(|p| p.steam_id.is_some()));
+    }
+}
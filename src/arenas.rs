@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+
+use chrono::{DateTime, Utc};
+
+use crate::steamid::SteamId;
+
+// Which free arena a match gets sent to. The dispatcher has already decided the match
+// gets played now, this only decides where.
+
+pub struct ArenaRequest<'a> {
+    // free arenas, in the map's priority order
+    pub free: &'a [usize],
+    // the first arena in the priority order, whether it's free or not
+    pub showcase: Option<usize>,
+    pub p1: SteamId,
+    pub p2: SteamId,
+    // one of the last two rounds of its side of the bracket
+    pub late: bool,
+    // when a match was last sent to each arena
+    pub last_used: &'a HashMap<usize, DateTime<Utc>>,
+    // where each player played their last match
+    pub last_arena: &'a HashMap<SteamId, usize>,
+}
+
+pub trait ArenaSelection {
+    fn name(&self) -> &'static str;
+    fn pick(&self, request: &ArenaRequest) -> Option<usize>;
+}
+
+/// First free arena in the priority order.
+pub struct FixedPriority;
+
+impl ArenaSelection for FixedPriority {
+    fn name(&self) -> &'static str {
+        "fixed priority"
+    }
+
+    fn pick(&self, request: &ArenaRequest) -> Option<usize> {
+        request.free.first().copied()
+    }
+}
+
+/// The arena that's been empty the longest, so play is spread over the whole map.
+pub struct LeastRecentlyUsed;
+
+impl ArenaSelection for LeastRecentlyUsed {
+    fn name(&self) -> &'static str {
+        "least recently used"
+    }
+
+    fn pick(&self, request: &ArenaRequest) -> Option<usize> {
+        // never used sorts first as None
+        request
+            .free
+            .iter()
+            .copied()
+            .min_by_key(|arena| request.last_used.get(arena))
+    }
+}
+
+/// Back to the arena one of the players just won in, so they don't have to move.
+pub struct KeepWinner;
+
+impl ArenaSelection for KeepWinner {
+    fn name(&self) -> &'static str {
+        "keep winner in arena"
+    }
+
+    fn pick(&self, request: &ArenaRequest) -> Option<usize> {
+        [request.p1, request.p2]
+            .iter()
+            .filter_map(|p| request.last_arena.get(p))
+            .find(|arena| request.free.contains(arena))
+            .or(request.free.first())
+            .copied()
+    }
+}
+
+/// Keeps the first arena of the priority order for the late rounds, where STV and the
+/// crowd are.
+pub struct Showcase;
+
+impl ArenaSelection for Showcase {
+    fn name(&self) -> &'static str {
+        "showcase late rounds"
+    }
+
+    fn pick(&self, request: &ArenaRequest) -> Option<usize> {
+        let showcase_free = request.showcase.filter(|s| request.free.contains(s));
+        if request.late {
+            if let Some(showcase) = showcase_free {
+                return Some(showcase);
+            }
+        }
+        request
+            .free
+            .iter()
+            .copied()
+            .find(|&arena| Some(arena) != request.showcase)
+    }
+}
+
+/// Any free arena.
+pub struct Random;
+
+impl ArenaSelection for Random {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn pick(&self, request: &ArenaRequest) -> Option<usize> {
+        if request.free.is_empty() {
+            return None;
+        }
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        Some(request.free[random as usize % request.free.len()])
+    }
+}
+
+pub fn selections() -> Vec<Box<dyn ArenaSelection>> {
+    vec![
+        Box::new(FixedPriority),
+        Box::new(LeastRecentlyUsed),
+        Box::new(KeepWinner),
+        Box::new(Showcase),
+        Box::new(Random),
+    ]
+}
+
+pub fn selection(name: &str) -> Option<Box<dyn ArenaSelection>> {
+    selections().into_iter().find(|s| s.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::challonge::testing::steam_id;
+
+    struct Maps {
+        last_used: HashMap<usize, DateTime<Utc>>,
+        last_arena: HashMap<SteamId, usize>,
+    }
+
+    impl Maps {
+        fn new() -> Self {
+            Maps {
+                last_used: HashMap::new(),
+                last_arena: HashMap::new(),
+            }
+        }
+
+        // arena 0 showcases, players 1 and 2 are in the match
+        fn request<'a>(&'a self, free: &'a [usize], late: bool) -> ArenaRequest<'a> {
+            ArenaRequest {
+                free,
+                showcase: Some(0),
+                p1: steam_id(1),
+                p2: steam_id(2),
+                late,
+                last_used: &self.last_used,
+                last_arena: &self.last_arena,
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_priority_takes_the_first_free() {
+        let maps = Maps::new();
+        assert_eq!(
+            FixedPriority.pick(&maps.request(&[3, 1, 2], false)),
+            Some(3)
+        );
+        assert_eq!(FixedPriority.pick(&maps.request(&[], false)), None);
+    }
+
+    #[test]
+    fn least_recently_used_prefers_unused_then_oldest() {
+        let mut maps = Maps::new();
+        let now = Utc::now();
+        maps.last_used.insert(1, now - Duration::minutes(5));
+        maps.last_used.insert(2, now - Duration::minutes(10));
+        assert_eq!(
+            LeastRecentlyUsed.pick(&maps.request(&[1, 2], false)),
+            Some(2)
+        );
+        assert_eq!(
+            LeastRecentlyUsed.pick(&maps.request(&[1, 2, 3], false)),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn keep_winner_goes_back_if_free() {
+        let mut maps = Maps::new();
+        maps.last_arena.insert(steam_id(2), 4);
+        assert_eq!(KeepWinner.pick(&maps.request(&[1, 4], false)), Some(4));
+        // taken, falls back to the first free
+        assert_eq!(KeepWinner.pick(&maps.request(&[1, 3], false)), Some(1));
+        maps.last_arena.insert(steam_id(1), 3);
+        assert_eq!(KeepWinner.pick(&maps.request(&[1, 3, 4], false)), Some(3));
+    }
+
+    #[test]
+    fn showcase_is_for_late_rounds() {
+        let maps = Maps::new();
+        assert_eq!(Showcase.pick(&maps.request(&[0, 1], true)), Some(0));
+        assert_eq!(Showcase.pick(&maps.request(&[0, 1], false)), Some(1));
+        // late but the showcase is busy
+        assert_eq!(Showcase.pick(&maps.request(&[1, 2], true)), Some(1));
+        // early rounds wait rather than take the showcase
+        assert_eq!(Showcase.pick(&maps.request(&[0], false)), None);
+    }
+
+    #[test]
+    fn random_only_picks_free_arenas() {
+        let maps = Maps::new();
+        for _ in 0..20 {
+            let arena = Random.pick(&maps.request(&[2, 5], false)).unwrap();
+            assert!(arena == 2 || arena == 5);
+        }
+        assert_eq!(Random.pick(&maps.request(&[], false)), None);
+    }
+
+    #[test]
+    fn selections_are_found_by_name() {
+        for s in selections() {
+            assert_eq!(selection(s.name()).unwrap().name(), s.name());
+        }
+        assert!(selection("nope").is_none());
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use challonge::Challonge;
+
+use crate::challonge::{get_entrants, get_matches, Entrant, FetchError, Match};
+use crate::participants::ParticipantStore;
+
+// don't ask challonge again sooner than this unless one of our own writes moved the
+// bracket
+const MIN_REFRESH: Duration = Duration::from_secs(2);
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Our copy of the challonge bracket. Dispatching works from this, challonge is only
+/// asked again after we report something or when the copy gets old.
+pub struct BracketCache {
+    pub matches: Vec<Match>,
+    pub entrants: HashMap<u64, Entrant>,
+    fetched: Option<Instant>,
+    // we reported something and challonge has advanced the bracket since
+    dirty: bool,
+    backoff: Duration,
+    backoff_until: Option<Instant>,
+}
+
+impl BracketCache {
+    pub fn new() -> Self {
+        BracketCache {
+            matches: vec![],
+            entrants: HashMap::new(),
+            fetched: None,
+            dirty: true,
+            backoff: INITIAL_BACKOFF,
+            backoff_until: None,
+        }
+    }
+
+    fn backing_off(&self) -> bool {
+        self.backoff_until
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    fn failed<T>(&mut self, e: FetchError) -> Result<T, String> {
+        match e {
+            FetchError::RateLimited => {
+                self.backoff_until = Some(Instant::now() + self.backoff);
+                let message = format!("challonge is rate limiting us, waiting {:?}", self.backoff);
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                Err(message)
+            }
+            FetchError::Failed(reason) => Err(format!("could not fetch the bracket: {}", reason)),
+        }
+    }
+
+    /// Fetches the matches if they could have changed. Errors leave the old copy in
+    /// place.
+    pub fn refresh_matches(&mut self, tid: &challonge::TournamentId) -> Result<bool, String> {
+        let recent = self.fetched.is_some_and(|t| t.elapsed() < MIN_REFRESH);
+        if self.backing_off() || (recent && !self.dirty) {
+            return Ok(false);
+        }
+        match get_matches(tid) {
+            Ok(matches) => {
+                self.matches = matches;
+                self.fetched = Some(Instant::now());
+                self.dirty = false;
+                self.backoff = INITIAL_BACKOFF;
+                Ok(true)
+            }
+            Err(e) => self.failed(e),
+        }
+    }
+
+    pub fn refresh_entrants(
+        &mut self,
+        c: &Challonge,
+        tc: &challonge::Tournament,
+        store: &mut ParticipantStore,
+    ) -> Result<(), String> {
+        if self.backing_off() {
+            return Ok(());
+        }
+        match get_entrants(c, tc, store) {
+            Ok(entrants) => {
+                self.entrants = entrants;
+                Ok(())
+            }
+            Err(e) => self.failed(e),
+        }
+    }
+
+    /// Applies a result we just reported so the match stops showing as open before
+    /// challonge is asked again.
+    pub fn reported(&mut self, match_id: u64, winner_pid: u64) {
+        if let Some(m) = self.matches.iter_mut().find(|m| m.id == match_id) {
+            let scores = if m.player1_id == Some(winner_pid) {
+                "1-0"
+            } else {
+                "0-1"
+            };
+            m.winner_id = Some(winner_pid);
+            m.scores_csv = Some(scores.to_string());
+            m.state = "complete".to_string();
+        }
+        self.dirty = true;
+    }
+
+    /// For writes whose effect we can't apply locally, like reopening a match.
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+}
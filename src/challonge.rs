@@ -1,89 +1,284 @@
-use std::{
-    collections::HashMap,
-    ops::{Add, Sub},
-};
+use std::collections::HashMap;
 
+use challonge::tournament::{GamePoints, RankedBy, Tournament, TournamentCreate, TournamentType};
 pub use challonge::Challonge;
-use challonge::{
-    tournament::{
-        GamePoints, RankedBy, Tournament, TournamentCreate, TournamentId, TournamentIncludes,
-        TournamentState, TournamentType,
-    },
-    MatchScore, MatchScores,
-};
-use chrono::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::participants::ParticipantStore;
+use crate::steamid::SteamId;
 
 pub const SUBDOMAIN: &str = "89c2a59aadab1761b8e29117";
+// minutes, challonge opens check-in this long before start_at
+pub const CHECK_IN_DURATION: u64 = 60;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Format {
+    SingleElimination,
+    DoubleElimination,
+    RoundRobin,
+    Swiss,
+}
 
-pub fn create_tournament(c: &Challonge, url: String, title: String) -> challonge::Tournament {
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Ranking {
+    MatchWins,
+    GameWins,
+    PointsScored,
+    PointsDifference,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum GrandFinals {
+    // the winners bracket finalist gets two chances, challonge's default
+    TwoChances,
+    SingleMatch,
+    Skip,
+}
+
+/// Everything we let admins choose when creating a tournament.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentOptions {
+    pub name: String,
+    // letters, numbers and underscores
+    pub url: String,
+    #[serde(default)]
+    pub description: String,
+    pub format: Format,
+    #[serde(default)]
+    pub hold_third_place_match: bool,
+    pub grand_finals: GrandFinals,
+    pub ranked_by: Ranking,
+    pub signup_cap: u64,
+    // swiss only
+    #[serde(default)]
+    pub swiss_rounds: u64,
+    #[serde(default)]
+    pub start_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub private: bool,
+}
+
+pub fn create_tournament(c: &Challonge, opts: &TournamentOptions) -> Result<Tournament, String> {
+    if opts.url.is_empty()
+        || !opts
+            .url
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+    {
+        return Err(format!(
+            "{:?} is not a valid url, use letters, numbers and underscores",
+            opts.url
+        ));
+    }
     let tc = TournamentCreate {
-        name: title.to_owned(),
-        tournament_type: TournamentType::SingleElimination,
-        url,
+        name: opts.name.clone(),
+        tournament_type: match opts.format {
+            Format::SingleElimination => TournamentType::SingleElimination,
+            Format::DoubleElimination => TournamentType::DoubleElimination,
+            Format::RoundRobin => TournamentType::RoundRobin,
+            Format::Swiss => TournamentType::Swiss,
+        },
+        url: opts.url.clone(),
         subdomain: SUBDOMAIN.to_string(),
-        description: "Test tournament created from challonge-rs".to_owned(),
+        description: opts.description.clone(),
         open_signup: false,
-        hold_third_place_match: false,
-        ranked_by: RankedBy::PointsScored,
-        show_rounds: false,
-        private: false,
+        hold_third_place_match: opts.hold_third_place_match,
+        ranked_by: match opts.ranked_by {
+            Ranking::MatchWins => RankedBy::MatchWins,
+            Ranking::GameWins => RankedBy::GameWins,
+            Ranking::PointsScored => RankedBy::PointsScored,
+            Ranking::PointsDifference => RankedBy::PointsDifference,
+        },
+        show_rounds: true,
+        private: opts.private,
         notify_users_when_matches_open: true,
         notify_users_when_the_tournament_ends: true,
         sequential_pairings: false,
-        signup_cap: 4,
-        start_at: Some(Utc::now().sub(Duration::days(1))),
-        check_in_duration: 60,
-        grand_finals_modifier: None,
+        signup_cap: opts.signup_cap,
+        start_at: opts.start_at,
+        check_in_duration: CHECK_IN_DURATION,
+        grand_finals_modifier: match opts.grand_finals {
+            GrandFinals::TwoChances => None,
+            GrandFinals::SingleMatch => Some("single match".to_string()),
+            GrandFinals::Skip => Some("skip".to_string()),
+        },
         swiss_points: GamePoints::default(),
-        swiss_rounds: 0,
+        swiss_rounds: opts.swiss_rounds,
         round_robin_points: GamePoints::default(),
         game_name: Some("mge".to_owned()),
     };
 
-    c.create_tournament(&tc).unwrap()
+    c.create_tournament(&tc).map_err(|e| format!("{:?}", e))
 }
 
-pub fn add_participant(tc: &Tournament, name: &String, steamid: &String) {
+/// Adds a participant, returning its challonge id.
+pub fn add_participant(tc: &Tournament, name: &String, steamid: &SteamId) -> Result<u64, String> {
     let mut mp = std::collections::HashMap::new();
     let api_key = std::fs::read_to_string("api_key.txt").unwrap();
     mp.insert("api_key", json!(api_key.trim()));
     mp.insert(
         "participant",
-        json!({"name": name, 
-                  "seed": 1,
-                  "misc": steamid}),
+        json!({"name": name,
+                  "misc": steamid.to_string()}),
     );
 
     let client = reqwest::blocking::Client::new();
     let post = client
-        .post(&format!(
+        .post(format!(
             "https://api.challonge.com/v1/tournaments/{}/participants.json",
             tc.id
         ))
         .json(&mp)
         .send()
-        .unwrap();
+        .map_err(|e| e.to_string())?;
 
     println!("{:?}", post);
+    if !post.status().is_success() {
+        return Err(format!("add participant: {}", post.status()));
+    }
+    let body: serde_json::Value = post.json().map_err(|e| e.to_string())?;
+    body["participant"]["id"]
+        .as_u64()
+        .ok_or("add participant: no id in the response".to_string())
+}
+
+/// Moves the participant to `seed`, challonge shifts everyone in between.
+pub fn set_seed(tc: &Tournament, participant_id: u64, seed: usize) -> Result<(), String> {
+    let mut mp = std::collections::HashMap::new();
+    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
+    mp.insert("api_key", json!(api_key.trim()));
+    mp.insert("participant", json!({ "seed": seed }));
+
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .put(format!(
+            "https://api.challonge.com/v1/tournaments/{}/participants/{}.json",
+            tc.id, participant_id
+        ))
+        .json(&mp)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("set seed: {}", resp.status()));
+    }
+    Ok(())
+}
+
+/// Sets start_at and sizes the check-in window so it opens right now.
+pub fn open_check_in(tc: &Tournament, start_at: DateTime<Utc>) -> Result<(), String> {
+    let duration = (start_at - Utc::now()).num_minutes().max(1);
+    let mut mp = std::collections::HashMap::new();
+    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
+    mp.insert("api_key", json!(api_key.trim()));
+    mp.insert(
+        "tournament",
+        json!({"start_at": start_at.to_rfc3339(),
+               "check_in_duration": duration}),
+    );
+
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .put(format!(
+            "https://api.challonge.com/v1/tournaments/{}.json",
+            tc.id
+        ))
+        .json(&mp)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("open check-in: {}", resp.status()));
+    }
+    Ok(())
+}
+
+pub fn check_in_participant(tc: &Tournament, participant_id: u64) -> Result<(), String> {
+    let mut mp = std::collections::HashMap::new();
+    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
+    mp.insert("api_key", api_key.trim());
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!(
+            "https://api.challonge.com/v1/tournaments/{}/participants/{}/check_in.json",
+            tc.id, participant_id
+        ))
+        .json(&mp)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("check in: {}", resp.status()));
+    }
+    Ok(())
 }
 
-pub fn start_tournament(tc: &Tournament) {
+/// Drops everyone who didn't check in from the bracket.
+pub fn process_check_ins(tc: &Tournament) -> Result<(), String> {
     let mut mp = std::collections::HashMap::new();
     let api_key = std::fs::read_to_string("api_key.txt").unwrap();
     mp.insert("api_key", api_key.trim());
     let client = reqwest::blocking::Client::new();
-    let put = client
-        .post(&format!(
+    let resp = client
+        .post(format!(
+            "https://api.challonge.com/v1/tournaments/{}/process_check_ins.json",
+            tc.id
+        ))
+        .json(&mp)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("process check-ins: {}", resp.status()));
+    }
+    Ok(())
+}
+
+pub fn start_tournament(tc: &Tournament) -> Result<(), String> {
+    let mut mp = std::collections::HashMap::new();
+    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
+    mp.insert("api_key", api_key.trim());
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!(
             "https://api.challonge.com/v1/tournaments/{}/start.json",
             tc.id
         ))
         .json(&mp)
         .send()
-        .unwrap();
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("start: {}", resp.status()));
+    }
+    Ok(())
 }
-use serde_json::{json, Value};
 
-pub fn update_match(tc: &Tournament, m: &Match, winner: &u64, scoreline: &str) {
+/// Closes the tournament on challonge, which also hands out the final ranks.
+pub fn finalize_tournament(tc: &Tournament) -> Result<(), String> {
+    let mut mp = std::collections::HashMap::new();
+    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
+    mp.insert("api_key", api_key.trim());
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!(
+            "https://api.challonge.com/v1/tournaments/{}/finalize.json",
+            tc.id
+        ))
+        .json(&mp)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("finalize: {}", resp.status()));
+    }
+    Ok(())
+}
+
+use serde_json::json;
+
+pub fn update_match(
+    tc: &Tournament,
+    m: &Match,
+    winner: &u64,
+    scoreline: &str,
+) -> Result<(), String> {
     let mut mp = std::collections::HashMap::new();
     let mut matches = std::collections::HashMap::new();
     matches.insert("scores_csv", json!(scoreline));
@@ -97,171 +292,303 @@ pub fn update_match(tc: &Tournament, m: &Match, winner: &u64, scoreline: &str) {
     let client = reqwest::blocking::Client::new();
 
     println!("reporting match");
-    let put = client
-        .put(&format!(
+    let resp = client
+        .put(format!(
             "https://api.challonge.com/v1/tournaments/{}/matches/{:?}.json",
             tc.id, m.id,
         ))
         .json(&mp)
         .send()
-        .unwrap();
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("update match {}: {}", m.id, resp.status()));
+    }
+    Ok(())
 }
 
-type SteamID = String;
+// (name, steamid)
+pub type NamedPlayer = (String, SteamId);
 
-pub fn report_match(c: &Challonge, tc: &Tournament, p1: SteamID, p2: SteamID) {
-    let matches = get_matches(&tc.id);
-    let participants = c.participant_index(&tc.id).unwrap();
-    let pid_to_name: HashMap<u64, (String, SteamID)> = participants
-        .0
-        .iter()
-        .map(|p| (p.id.0, (p.name.clone(), p.misc.clone())))
-        .collect::<std::collections::HashMap<_, _>>();
+pub fn reopen_match(tc: &Tournament, match_id: u64) -> Result<(), String> {
+    let mut mp = std::collections::HashMap::new();
+    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
+    mp.insert("api_key", api_key.trim().to_string());
+
+    let client = reqwest::blocking::Client::new();
+
+    println!("reopening match {}", match_id);
+    let resp = client
+        .post(format!(
+            "https://api.challonge.com/v1/tournaments/{}/matches/{}/reopen.json",
+            tc.id, match_id,
+        ))
+        .json(&mp)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("reopen match {}: {}", match_id, resp.status()));
+    }
+    Ok(())
+}
+
+/// Flags the match as being played, which is how we find it again after a restart.
+pub fn mark_underway(tc: &Tournament, match_id: u64, underway: bool) -> Result<(), String> {
+    let mut mp = std::collections::HashMap::new();
+    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
+    mp.insert("api_key", api_key.trim().to_string());
+    let action = if underway {
+        "mark_as_underway"
+    } else {
+        "unmark_as_underway"
+    };
 
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!(
+            "https://api.challonge.com/v1/tournaments/{}/matches/{}/{}.json",
+            tc.id, match_id, action,
+        ))
+        .json(&mp)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("{}: {}", action, resp.status()));
+    }
+    Ok(())
+}
+
+/// Reports the open match between the two players, returning the match and the winner's
+/// participant id if there was one and challonge took the result.
+pub fn report_match(
+    tc: &Tournament,
+    matches: &[Match],
+    entrants: &HashMap<u64, Entrant>,
+    p1: SteamId,
+    p2: SteamId,
+) -> Result<Option<(u64, u64)>, String> {
     for m in matches {
         if m.winner_id.is_some() {
-            println!("skipping finished match");
             continue;
         }
-        match (m.player1_id, m.player2_id) {
-            (Some(mp1id), Some(mp2id)) => {
-                // if the match has both a player1 and player2
-                let mp1 = pid_to_name.get(&mp1id);
-                let mp2 = pid_to_name.get(&mp2id);
-                match (mp1, mp2) {
-                    // (name, steamid), (name, steamid)
-                    (Some(mp1), Some(mp2)) => {
-                        println!("checking match between {} and {}", mp1.0, mp2.0);
-                        if mp1.1 == p1 && mp2.1 == p2 {
-                            println!("reporting match between {} and {}", mp1.0, mp2.0);
-                            update_match(&tc, &m, &m.player1_id.unwrap(), "1-0");
-                        } else if (mp1.1 == p2 && mp2.1 == p1) {
-                            println!("reporting match between {} and {}", mp1.0, mp2.0);
-                            update_match(&tc, &m, &m.player2_id.unwrap(), "0-1");
-                        }
-                    }
-                    _ => {}
+        // if the match has both a player1 and player2
+        if let (Some(mp1id), Some(mp2id)) = (m.player1_id, m.player2_id) {
+            if let (Some(mp1), Some(mp2)) = (entrants.get(&mp1id), entrants.get(&mp2id)) {
+                if mp1.steam_id == Some(p1) && mp2.steam_id == Some(p2) {
+                    println!("reporting match between {} and {}", mp1.name, mp2.name);
+                    update_match(tc, m, &mp1id, "1-0")?;
+                    return Ok(Some((m.id, mp1id)));
+                } else if mp1.steam_id == Some(p2) && mp2.steam_id == Some(p1) {
+                    println!("reporting match between {} and {}", mp1.name, mp2.name);
+                    update_match(tc, m, &mp2id, "0-1")?;
+                    return Ok(Some((m.id, mp2id)));
                 }
             }
-            _ => {}
         }
     }
+    Ok(None)
 }
 
-pub fn pending_matches(
+#[derive(Debug, Clone)]
+pub struct Entrant {
+    pub name: String,
+    // None for participants rustmge didn't add and whose misc isn't a steamid
+    pub steam_id: Option<SteamId>,
+    pub final_rank: Option<u64>,
+    pub checked_in: bool,
+    // false once dropped by process_check_ins
+    pub active: bool,
+}
+
+pub fn get_entrants(
     c: &Challonge,
     tc: &Tournament,
-) -> Vec<((String, String), (String, String))> {
-    let index = get_matches(&tc.id);
-
-    let participants = c.participant_index(&tc.id).unwrap();
-
-    let pid_to_name: std::collections::HashMap<u64, (String, SteamID)> = participants
+    store: &mut ParticipantStore,
+) -> Result<HashMap<u64, Entrant>, FetchError> {
+    let participants = c
+        .participant_index(&tc.id)
+        .map_err(|e| FetchError::Failed(format!("{:?}", e)))?;
+    let tournament = tc.id.to_string();
+
+    Ok(participants
         .0
-        .iter()
-        .map(|p| (p.id.0, (p.name.clone(), p.misc.clone())))
-        .collect::<std::collections::HashMap<_, _>>();
+        .into_iter()
+        .map(|p| {
+            let steam_id = match store.get(p.id.0) {
+                Some(known) => Some(known.steam_id),
+                None => {
+                    let steam_id: Option<SteamId> = p.misc.parse().ok();
+                    if let Some(steam_id) = steam_id {
+                        store.insert(p.id.0, &tournament, steam_id, &p.name);
+                    }
+                    steam_id
+                }
+            };
+            (
+                p.id.0,
+                Entrant {
+                    name: p.name,
+                    steam_id,
+                    final_rank: p.final_rank,
+                    checked_in: p.checked_in,
+                    active: p.active,
+                },
+            )
+        })
+        .collect())
+}
 
+pub fn pending_from(
+    matches: &[Match],
+    entrants: &HashMap<u64, Entrant>,
+) -> Vec<(u64, NamedPlayer, NamedPlayer)> {
     let mut pending_matches = vec![];
-    for m in index {
+    for m in matches {
         if m.winner_id.is_some() {
             continue;
         }
         if m.player1_id.is_none() || m.player2_id.is_none() {
             continue;
         }
-        let p1 = pid_to_name.get(&m.player1_id.unwrap());
-        let p2 = pid_to_name.get(&m.player2_id.unwrap());
-        match (p1, p2) {
-            (Some(p1), Some(p2)) => {
-                pending_matches.push((p1.clone(), p2.clone()));
+        let p1 = entrants.get(&m.player1_id.unwrap());
+        let p2 = entrants.get(&m.player2_id.unwrap());
+        if let (Some(p1), Some(p2)) = (p1, p2) {
+            if let (Some(p1_id), Some(p2_id)) = (p1.steam_id, p2.steam_id) {
+                pending_matches.push((m.id, (p1.name.clone(), p1_id), (p2.name.clone(), p2_id)));
             }
-            _ => {}
         }
     }
     pending_matches
 }
 
-pub fn main() {
-    let api_key = std::fs::read_to_string("api_key.txt").unwrap();
-    let c = Challonge::new("tommylt3", api_key.trim());
-    let cmd = std::env::args().nth(1).unwrap_or("debug".to_string());
-
-    //delete_test_tournaments(&c);
-    let mut tc;
-
-    if (cmd == "create") {
-        tc = create_tournament(&c, "mge1".to_string(), "weekly tournament 1".to_string());
-
-        let participants = vec![
-            ("hallu".to_string(), "76561198000000000".to_string()),
-            ("test".to_string(), "76561198000000000".to_string()),
-            ("jt".to_string(), "76561198000000000".to_string()),
-            ("mang0".to_string(), "76561198000000000".to_string()),
-            ("tommy".to_string(), "76561198000000000".to_string()),
-            ("cutix".to_string(), "76561198000000000".to_string()),
-        ];
-
-        for (name, steamid) in participants {
-            //add_participant(&c, &tc, name, steamid);
-        }
-    } else {
-        tc = c
-            .get_tournament(
-                &TournamentId::Url(SUBDOMAIN.to_string(), "mge1".to_string()),
-                &TournamentIncludes::All,
-            )
-            .unwrap();
-    }
-
-    if cmd == "start" {
-        c.tournament_start(&tc.id, &TournamentIncludes::All)
-            .unwrap()
-    }
-
-    let pending = pending_matches(&c, &tc);
-    println!("{:?}", pending);
-
-    // println!("Tournament created with id: {}", tc.id);
-    println!("Hello, world!");
-}
-
 #[derive(serde::Deserialize, Debug)]
 struct MatchLike {
     #[serde(rename = "match")]
     pub mat: Match,
 }
-#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[derive(serde::Deserialize, Debug, Clone)]
 #[serde(tag = "match")]
 pub struct Match {
     pub id: u64,
     pub player1_id: Option<u64>,
     pub player2_id: Option<u64>,
     pub winner_id: Option<u64>,
+    // negative rounds are the losers bracket
+    pub round: i64,
+    pub state: String,
+    pub scores_csv: Option<String>,
+    pub suggested_play_order: Option<u64>,
+    pub underway_at: Option<String>,
+    pub completed_at: Option<String>,
+    // the matches whose winner (or loser, in double elimination) plays in this one
+    pub player1_prereq_match_id: Option<u64>,
+    pub player2_prereq_match_id: Option<u64>,
+}
+
+/// Matches in the shape challonge's matches endpoint returns them.
+pub fn parse_matches(text: &str) -> Result<Vec<Match>, serde_json::Error> {
+    let matches: Vec<MatchLike> = serde_json::from_str(text)?;
+    Ok(matches.into_iter().map(|m| m.mat).collect())
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    // challonge answered 429, back off before asking again
+    RateLimited,
+    Failed(String),
 }
 
-pub fn get_matches(tid: &challonge_api::TournamentId) -> Vec<Match> {
+pub fn get_matches(tid: &challonge_api::TournamentId) -> Result<Vec<Match>, FetchError> {
     let client = reqwest::blocking::Client::new();
     let mut url = reqwest::Url::parse(&format!(
         "https://api.challonge.com/v1/tournaments/{}/matches.json",
-        tid.to_string()
+        tid
     ))
     .unwrap();
 
     {
         let api_key = std::fs::read_to_string("api_key.txt").unwrap();
         let mut pairs = url.query_pairs_mut();
-        pairs.append_pair("api_key", &api_key.trim());
+        pairs.append_pair("api_key", api_key.trim());
         pairs.append_pair("state", "all");
     }
 
-    let index = client.get(url.as_str()).send().unwrap().text();
-    let matches: Vec<MatchLike> = serde_json::from_str(&index.unwrap()).unwrap();
-    let matches = matches
-        .iter()
-        .map(|m| m.mat)
-        .filter(|m| m.winner_id.is_none())
-        .collect::<Vec<_>>();
+    let resp = client
+        .get(url.as_str())
+        .send()
+        .map_err(|e| FetchError::Failed(e.to_string()))?;
+    if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(FetchError::RateLimited);
+    }
+    if !resp.status().is_success() {
+        return Err(FetchError::Failed(format!("matches: {}", resp.status())));
+    }
+    let matches: Vec<MatchLike> = resp.json().map_err(|e| FetchError::Failed(e.to_string()))?;
+    Ok(matches.into_iter().map(|m| m.mat).collect())
+}
+
+/// Fixtures for tests that need a bracket without asking challonge for one.
+#[cfg(test)]
+pub mod testing {
+    use std::collections::HashMap;
+
+    use super::{Entrant, Match};
+    use crate::steamid::SteamId;
+
+    /// The steamid of account `n`, `[U:1:n]`.
+    pub fn steam_id(n: u64) -> SteamId {
+        format!("[U:1:{}]", n).parse().unwrap()
+    }
+
+    /// Participants 1 to `count`, participant n being account n.
+    pub fn entrants(count: u64) -> HashMap<u64, Entrant> {
+        (1..=count)
+            .map(|pid| {
+                let entrant = Entrant {
+                    name: format!("p{}", pid),
+                    steam_id: Some(steam_id(pid)),
+                    final_rank: None,
+                    checked_in: true,
+                    active: true,
+                };
+                (pid, entrant)
+            })
+            .collect()
+    }
 
-    matches
+    impl Match {
+        /// A pending match with nobody in it, tests fill in the rest.
+        pub fn blank(id: u64, round: i64) -> Self {
+            Match {
+                id,
+                player1_id: None,
+                player2_id: None,
+                winner_id: None,
+                round,
+                state: "pending".to_string(),
+                scores_csv: None,
+                suggested_play_order: None,
+                underway_at: None,
+                completed_at: None,
+                player1_prereq_match_id: None,
+                player2_prereq_match_id: None,
+            }
+        }
+
+        /// A match between two participants, `winner` set once it's been played.
+        pub fn between(id: u64, round: i64, p1: u64, p2: u64, winner: Option<u64>) -> Self {
+            Match {
+                player1_id: Some(p1),
+                player2_id: Some(p2),
+                winner_id: winner,
+                state: if winner.is_some() { "complete" } else { "open" }.to_string(),
+                ..Match::blank(id, round)
+            }
+        }
+
+        pub fn after(mut self, p1: u64, p2: u64) -> Self {
+            self.player1_prereq_match_id = Some(p1);
+            self.player2_prereq_match_id = Some(p2);
+            self
+        }
+    }
 }
@@ -0,0 +1,247 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::challonge::Match;
+
+// Decides which ready match gets the next free arena. Only matters once there are more
+// ready matches than arenas, which is exactly when a bad order drags the day out.
+
+/// How a match sits in the bracket, worked out from challonge's prerequisite ids.
+pub struct BracketGraph {
+    // matches that get a player from this one
+    next: HashMap<u64, Vec<u64>>,
+    // longest chain of matches from this one to the end of the bracket, itself included
+    height: HashMap<u64, u32>,
+    // how many matches can't be played until this one is
+    blocking: HashMap<u64, usize>,
+}
+
+impl BracketGraph {
+    pub fn new(matches: &[Match]) -> Self {
+        let mut next: HashMap<u64, Vec<u64>> = HashMap::new();
+        for m in matches {
+            for prereq in [m.player1_prereq_match_id, m.player2_prereq_match_id]
+                .into_iter()
+                .flatten()
+            {
+                next.entry(prereq).or_default().push(m.id);
+            }
+        }
+        let mut graph = BracketGraph {
+            next,
+            height: HashMap::new(),
+            blocking: HashMap::new(),
+        };
+        for m in matches {
+            graph.fill_height(m.id);
+            let blocking = graph.dependents(m.id).len();
+            graph.blocking.insert(m.id, blocking);
+        }
+        graph
+    }
+
+    fn fill_height(&mut self, id: u64) -> u32 {
+        if let Some(&height) = self.height.get(&id) {
+            return height;
+        }
+        let next = self.next.get(&id).cloned().unwrap_or_default();
+        let height = 1 + next
+            .into_iter()
+            .map(|n| self.fill_height(n))
+            .max()
+            .unwrap_or(0);
+        self.height.insert(id, height);
+        height
+    }
+
+    /// Every match that gets a player, directly or further down, from this one.
+    pub fn dependents(&self, id: u64) -> Vec<u64> {
+        let mut seen = vec![];
+        let mut todo = vec![id];
+        while let Some(id) = todo.pop() {
+            for &n in self.next.get(&id).into_iter().flatten() {
+                if !seen.contains(&n) {
+                    seen.push(n);
+                    todo.push(n);
+                }
+            }
+        }
+        seen
+    }
+
+    pub fn height(&self, id: u64) -> u32 {
+        self.height.get(&id).copied().unwrap_or(1)
+    }
+
+    pub fn blocking(&self, id: u64) -> usize {
+        self.blocking.get(&id).copied().unwrap_or(0)
+    }
+}
+
+pub trait DispatchPolicy {
+    fn name(&self) -> &'static str;
+    /// Higher goes first, ties keep the order the matches became ready in.
+    fn priority(&self, m: &Match, graph: &BracketGraph) -> [i64; 3];
+}
+
+/// First come, first served.
+pub struct Fifo;
+
+impl DispatchPolicy for Fifo {
+    fn name(&self) -> &'static str {
+        "fifo"
+    }
+
+    fn priority(&self, _m: &Match, _graph: &BracketGraph) -> [i64; 3] {
+        [0, 0, 0]
+    }
+}
+
+/// The match with the longest chain still behind it, since that chain is what the
+/// tournament can't finish before.
+pub struct CriticalPath;
+
+impl DispatchPolicy for CriticalPath {
+    fn name(&self) -> &'static str {
+        "critical path"
+    }
+
+    fn priority(&self, m: &Match, graph: &BracketGraph) -> [i64; 3] {
+        [
+            graph.height(m.id) as i64,
+            graph.blocking(m.id) as i64,
+            (m.round < 0) as i64,
+        ]
+    }
+}
+
+/// The match holding up the most other matches.
+pub struct MostBlocking;
+
+impl DispatchPolicy for MostBlocking {
+    fn name(&self) -> &'static str {
+        "most blocking"
+    }
+
+    fn priority(&self, m: &Match, graph: &BracketGraph) -> [i64; 3] {
+        [graph.blocking(m.id) as i64, graph.height(m.id) as i64, 0]
+    }
+}
+
+/// Losers bracket first so it keeps up with the winners side, then by critical path.
+pub struct LosersCatchUp;
+
+impl DispatchPolicy for LosersCatchUp {
+    fn name(&self) -> &'static str {
+        "losers catch up"
+    }
+
+    fn priority(&self, m: &Match, graph: &BracketGraph) -> [i64; 3] {
+        [
+            (m.round < 0) as i64,
+            graph.height(m.id) as i64,
+            graph.blocking(m.id) as i64,
+        ]
+    }
+}
+
+pub fn policies() -> Vec<Box<dyn DispatchPolicy>> {
+    vec![
+        Box::new(CriticalPath),
+        Box::new(Fifo),
+        Box::new(MostBlocking),
+        Box::new(LosersCatchUp),
+    ]
+}
+
+pub fn policy(name: &str) -> Option<Box<dyn DispatchPolicy>> {
+    policies().into_iter().find(|p| p.name() == name)
+}
+
+/// Puts `items` in the order `policy` wants them played, `id` gives each one's match.
+pub fn sort<T>(
+    policy: &dyn DispatchPolicy,
+    matches: &[Match],
+    items: &mut [T],
+    id: impl Fn(&T) -> u64,
+) {
+    let graph = BracketGraph::new(matches);
+    items.sort_by_cached_key(|item| {
+        let id = id(item);
+        let key = matches
+            .iter()
+            .find(|m| m.id == id)
+            .map(|m| policy.priority(m, &graph))
+            .unwrap_or_default();
+        Reverse(key)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // double elimination with four players: 1 and 2 winners round 1, 3 the winners
+    // final, 4 and 5 the losers bracket, 6 the grand final
+    fn double_elim() -> Vec<Match> {
+        vec![
+            Match::blank(1, 1),
+            Match::blank(2, 1),
+            Match::blank(3, 2).after(1, 2),
+            Match::blank(4, -1).after(1, 2),
+            Match::blank(5, -2).after(3, 4),
+            Match::blank(6, 3).after(3, 5),
+        ]
+    }
+
+    fn sorted(policy: &dyn DispatchPolicy, mut ids: Vec<u64>) -> Vec<u64> {
+        sort(policy, &double_elim(), &mut ids, |&id| id);
+        ids
+    }
+
+    #[test]
+    fn height_counts_the_longest_chain() {
+        let graph = BracketGraph::new(&double_elim());
+        let heights: Vec<u32> = (1..=6).map(|id| graph.height(id)).collect();
+        assert_eq!(heights, vec![4, 4, 3, 3, 2, 1]);
+        // not in the bracket
+        assert_eq!(graph.height(99), 1);
+    }
+
+    #[test]
+    fn dependents_and_blocking() {
+        let graph = BracketGraph::new(&double_elim());
+        let mut dependents = graph.dependents(1);
+        dependents.sort();
+        assert_eq!(dependents, vec![3, 4, 5, 6]);
+        assert_eq!(graph.blocking(1), 4);
+        assert_eq!(graph.blocking(3), 2);
+        assert_eq!(graph.blocking(6), 0);
+        assert!(graph.dependents(6).is_empty());
+    }
+
+    #[test]
+    fn critical_path_goes_deepest_first() {
+        assert_eq!(sorted(&CriticalPath, vec![6, 5, 1]), vec![1, 5, 6]);
+        // same height and blocking, the losers side breaks the tie
+        assert_eq!(sorted(&CriticalPath, vec![3, 4]), vec![4, 3]);
+    }
+
+    #[test]
+    fn fifo_keeps_the_order() {
+        assert_eq!(sorted(&Fifo, vec![6, 5, 1]), vec![6, 5, 1]);
+    }
+
+    #[test]
+    fn losers_catch_up_puts_losers_first() {
+        assert_eq!(sorted(&LosersCatchUp, vec![1, 6, 5]), vec![5, 1, 6]);
+    }
+
+    #[test]
+    fn policies_by_name() {
+        for p in policies() {
+            assert_eq!(policy(p.name()).map(|p| p.name()), Some(p.name()));
+        }
+        assert!(policy("nonsense").is_none());
+    }
+}
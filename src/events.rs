@@ -0,0 +1,285 @@
+use std::collections::VecDeque;
+
+use actix::prelude::*;
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use serde::{Deserialize, Serialize};
+
+use crate::public::{PublicUpdate, Standing};
+use crate::server::Tournament;
+use crate::AppState;
+
+const MAX_EVENTS: usize = 1000;
+
+// names only, like the rest of the public api
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventPlayer {
+    pub name: String,
+}
+
+/// What spectators get to see. Built from the game server messages but never a copy of
+/// them, so nothing like api keys or control messages can leak out.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all_fields = "camelCase")]
+pub enum SpectatorEvent {
+    TournamentStarted {
+        tournament: String,
+    },
+    TournamentStopped {
+        tournament: String,
+    },
+    TournamentPaused {
+        tournament: String,
+    },
+    TournamentResumed {
+        tournament: String,
+    },
+    TournamentFinished {
+        tournament: String,
+        placements: Vec<Standing>,
+    },
+    MatchAssigned {
+        arena: i32,
+        p1: EventPlayer,
+        p2: EventPlayer,
+    },
+    MatchBegan {
+        arena: Option<i32>,
+        p1: EventPlayer,
+        p2: EventPlayer,
+    },
+    ScoreUpdate {
+        arena: i32,
+        p1_score: i32,
+        p2_score: i32,
+    },
+    MatchResult {
+        arena: i32,
+        winner: EventPlayer,
+        loser: EventPlayer,
+    },
+    MatchCancelled {
+        arena: i32,
+    },
+    MatchReopened {
+        match_id: u64,
+    },
+    ResultUndone {
+        match_id: u64,
+        winner: EventPlayer,
+        loser: EventPlayer,
+    },
+    ResultCorrected {
+        match_id: u64,
+        winner: EventPlayer,
+        loser: EventPlayer,
+    },
+    // not stored, sent when a resume asks for events we no longer have
+    HistoryTruncated {
+        oldest_id: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventEnvelope {
+    pub id: u64,
+    pub time: String,
+    #[serde(flatten)]
+    pub event: SpectatorEvent,
+}
+
+impl EventEnvelope {
+    pub fn to_update(&self) -> PublicUpdate {
+        PublicUpdate(serde_json::to_string(self).unwrap())
+    }
+}
+
+pub struct EventLog {
+    next_id: u64,
+    events: VecDeque<EventEnvelope>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        // ids carry the time the log was made in the high digits so they keep going up
+        // across restarts, and a resume from an earlier run is seen as truncated
+        let epoch = chrono::Utc::now().timestamp_millis().max(0) as u64 * 1000;
+        EventLog {
+            next_id: epoch + 1,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, event: SpectatorEvent) -> &EventEnvelope {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(EventEnvelope {
+            id: self.next_id,
+            time: chrono::Utc::now().to_rfc3339(),
+            event,
+        });
+        self.next_id += 1;
+        self.events.back().unwrap()
+    }
+
+    /// Everything after `since`, prefixed with a HistoryTruncated notice if some of it
+    /// has already been dropped or `since` isn't one of ours.
+    pub fn since(&self, since: u64) -> Vec<EventEnvelope> {
+        let mut out = vec![];
+        let oldest = self.events.front().map_or(self.next_id, |e| e.id);
+        if since.saturating_add(1) < oldest || since >= self.next_id {
+            out.push(EventEnvelope {
+                id: oldest - 1,
+                time: chrono::Utc::now().to_rfc3339(),
+                event: SpectatorEvent::HistoryTruncated { oldest_id: oldest },
+            });
+        }
+        out.extend(self.events.iter().filter(|e| e.id > since).cloned());
+        out
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SubscribeEvents {
+    pub addr: Recipient<PublicUpdate>,
+    pub since: Option<u64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct UnsubscribeEvents {
+    pub addr: Recipient<PublicUpdate>,
+}
+
+pub struct EventWs {
+    addr: Addr<Tournament>,
+    since: Option<u64>,
+}
+
+impl Actor for EventWs {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.addr.do_send(SubscribeEvents {
+            addr: ctx.address().recipient(),
+            since: self.since,
+        });
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.addr.do_send(UnsubscribeEvents {
+            addr: ctx.address().recipient(),
+        });
+    }
+}
+
+impl Handler<PublicUpdate> for EventWs {
+    type Result = ();
+
+    fn handle(&mut self, msg: PublicUpdate, ctx: &mut Self::Context) {
+        ctx.text(msg.0);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for EventWs {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        // read-only, anything the client sends is ignored
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            _ => (),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    since: Option<u64>,
+}
+
+/// `/events?since=<id>` replays everything after `id` before going live.
+pub async fn events_route(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    query: web::Query<EventsQuery>,
+    stream: web::Payload,
+) -> Result<HttpResponse, Error> {
+    ws::start(
+        EventWs {
+            addr: data.tournment.clone(),
+            since: query.since,
+        },
+        &req,
+        stream,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started() -> SpectatorEvent {
+        SpectatorEvent::TournamentStarted {
+            tournament: "mge".to_string(),
+        }
+    }
+
+    fn truncated(events: &[EventEnvelope]) -> bool {
+        matches!(
+            events.first().map(|e| &e.event),
+            Some(SpectatorEvent::HistoryTruncated { .. })
+        )
+    }
+
+    #[test]
+    fn replays_after_since() {
+        let mut log = EventLog::new();
+        let first = log.push(started()).id;
+        let second = log.push(started()).id;
+        let replay = log.since(first);
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].id, second);
+        assert!(log.since(second).is_empty());
+    }
+
+    #[test]
+    fn since_from_an_earlier_run_is_truncated() {
+        let mut log = EventLog::new();
+        log.push(started());
+        assert!(truncated(&log.since(5)));
+        assert!(truncated(&EventLog::new().since(5)));
+    }
+
+    #[test]
+    fn since_ahead_of_the_log_is_truncated() {
+        let mut log = EventLog::new();
+        let id = log.push(started()).id;
+        assert!(truncated(&log.since(id + 10)));
+    }
+
+    #[test]
+    fn dropped_events_are_truncated() {
+        let mut log = EventLog::new();
+        let first = log.push(started()).id;
+        // the one right after `first` goes too
+        for _ in 0..=MAX_EVENTS {
+            log.push(started());
+        }
+        let replay = log.since(first);
+        assert!(truncated(&replay));
+        assert_eq!(replay.len(), MAX_EVENTS + 1);
+    }
+}
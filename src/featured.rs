@@ -0,0 +1,35 @@
+use actix::prelude::*;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+
+/// The match casters should be watching. Names only, this goes out on the public page
+/// and the stream overlay.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeaturedMatch {
+    pub match_id: u64,
+    pub round: i64,
+    // None until it's been sent to an arena
+    pub arena: Option<i32>,
+    pub p1: String,
+    pub p2: String,
+    pub p1_score: i32,
+    pub p2_score: i32,
+    // picked by an admin rather than by round and rating
+    pub pinned: bool,
+    // "connect host:port" for the server's STV
+    pub stv: Option<String>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Option<FeaturedMatch>")]
+pub struct GetFeatured;
+
+pub async fn featured_route(data: web::Data<AppState>) -> HttpResponse {
+    match data.tournment.send(GetFeatured).await {
+        Ok(featured) => HttpResponse::Ok().json(featured),
+        Err(e) => HttpResponse::ServiceUnavailable().body(e.to_string()),
+    }
+}
@@ -1,72 +1,171 @@
 extern crate challonge as challonge_api;
 
-use std::collections::{HashMap, HashSet};
-
 use actix::{Actor, AsyncContext, StreamHandler};
-use actix_files::{Files, NamedFile};
-use actix_web::{
-    get, guard::All, post, web, App, Error, HttpRequest, HttpResponse, HttpServer, Responder,
-};
+use actix_files::NamedFile;
+use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpServer, Responder};
 use actix_web_actors::ws;
 use serde::{Deserialize, Serialize};
+mod archive;
+mod arenas;
+mod bracket;
 mod challonge;
+mod dispatch;
+mod events;
+mod featured;
+mod overlay;
+mod participants;
+mod public;
+mod ratings;
+mod reconcile;
+mod resume;
+mod scheduler;
+mod seeding;
 mod server;
+mod simulate;
+mod steamid;
+mod templates;
+
+use crate::steamid::SteamId;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct Player {
-    steamId: String,
+    steam_id: SteamId,
     name: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(tag = "type", content = "payload")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all_fields = "camelCase")]
 enum MessagePayload {
     // receiving
     ServerHello {
-        apiKey: String,
-        serverNum: String,
-        serverHost: String,
-        serverPort: String,
-        stvPort: String,
+        api_key: String,
+        server_num: String,
+        server_host: String,
+        server_port: String,
+        stv_port: String,
     },
     // sending
     MatchDetails {
-        arenaId: i32,
-        p1Id: String,
-        p2Id: String,
+        arena_id: i32,
+        p1_id: SteamId,
+        p2_id: SteamId,
     },
     MatchBegan {
-        p1Id: String,
-        p2Id: String,
+        p1_id: SteamId,
+        p2_id: SteamId,
     },
     TournamentStart {},
     TournamentStop {},
+    TournamentFinished {
+        placements: Vec<archive::Placement>,
+    },
     MatchResults {
-        winner: String,
-        loser: String,
+        winner: SteamId,
+        loser: SteamId,
         finished: bool,
         arena: i32,
     },
     MatchCancel {
-        delinquents: Vec<String>,
-        arrived: String,
+        #[serde(deserialize_with = "steamid::skip_invalid")]
+        delinquents: Vec<SteamId>,
+        #[serde(
+            default,
+            deserialize_with = "steamid::optional",
+            serialize_with = "steamid::serialize_optional"
+        )]
+        arrived: Option<SteamId>,
         arena: i32,
     },
     UsersInServer {
+        #[serde(deserialize_with = "steamid::skip_invalid")]
         players: Vec<Player>,
     },
     Error {
         message: String,
     },
     SetMatchScore {
-        arenaId: i32,
-        p1Score: i32,
-        p2Score: i32,
+        arena_id: i32,
+        p1_score: i32,
+        p2_score: i32,
+    },
+    // admin only
+    Dashboard {
+        state: Box<server::Dashboard>,
+    },
+    DisputeResult {
+        match_id: u64,
+        reason: String,
+    },
+    ReopenMatch {
+        match_id: u64,
+    },
+    UndoLastResult {},
+    // sends a match a game server called off back out
+    RetryMatch {
+        match_id: u64,
+    },
+    CorrectResult {
+        match_id: u64,
+        winner: SteamId,
+    },
+    OpenCheckIn {},
+    LockRoster {},
+    StartBracket {},
+    // sent on to the game servers as well
+    PauseTournament {},
+    ResumeTournament {},
+    FinishTournament {},
+    CreateTournament {
+        options: challonge::TournamentOptions,
+    },
+    SaveTemplate {
+        name: String,
+        options: challonge::TournamentOptions,
+        #[serde(default)]
+        schedule: Option<scheduler::Schedule>,
+    },
+    DeleteTemplate {
+        name: String,
+    },
+    SetMinRest {
+        seconds: i64,
+    },
+    // lets the player be sent to their next match before their rest is up
+    SkipRest {
+        steam_id: SteamId,
+    },
+    // round None sets the default, selection None drops a round's own selection
+    SetArenaSelection {
+        #[serde(default)]
+        selection: Option<String>,
+        #[serde(default)]
+        round: Option<i64>,
+    },
+    // None goes back to picking it automatically
+    FeatureMatch {
+        #[serde(default)]
+        match_id: Option<u64>,
+    },
+    SetDispatchPolicy {
+        policy: String,
+    },
+    // None clears the override
+    SetRating {
+        steam_id: SteamId,
+        rating: Option<f64>,
+    },
+    // from the in-game check-in command
+    CheckIn {
+        steam_id: SteamId,
+        name: String,
+    },
+    CheckInConfirmed {
+        steam_id: SteamId,
     },
 }
 
 struct AppState {
-    app_name: String,
     tournment: actix::Addr<server::Tournament>,
 }
 
@@ -79,6 +178,12 @@ struct ServerWs {
 }
 impl Actor for ServerWs {
     type Context = ws::WebsocketContext<Self>;
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.addr.do_send(server::Disconnect {
+            addr: ctx.address(),
+        });
+    }
 }
 
 #[derive(Message)]
@@ -149,24 +254,37 @@ async fn index() -> impl Responder {
     NamedFile::open_async("./static/index.html").await.unwrap()
 }
 
-use actix_web::rt::task;
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("simulate") {
+        simulate::run(&args[1..]);
+        return Ok(());
+    }
+
     // read api_key.txt
     let api_key = std::fs::read_to_string("api_key.txt").unwrap();
-    let c = challonge::Challonge::new("tommylt3", &api_key.trim());
+    let c = challonge::Challonge::new("tommylt3", api_key.trim());
     let tournament = Tournament::new(c).start();
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(AppState {
-                app_name: String::from("Actix-web"),
                 tournment: tournament.clone(),
             }))
             .route("/tf2serverep", web::get().to(server_route))
             .route("/admin", web::get().to(admin))
             .route("/", web::get().to(index))
+            .route("/api/public", web::get().to(public::public_state_route))
+            .route(
+                "/api/leaderboard",
+                web::get().to(ratings::leaderboard_route),
+            )
+            .route("/api/featured", web::get().to(featured::featured_route))
+            .route("/api/overlay", web::get().to(overlay::overlay_route))
+            .route("/overlay/{page}", web::get().to(overlay::overlay_page))
+            .route("/public/ws", web::get().to(public::public_ws_route))
+            .route("/events", web::get().to(events::events_route))
     })
     .bind(("0.0.0.0", 8080))?
     .run()
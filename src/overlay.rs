@@ -0,0 +1,113 @@
+use actix_files::NamedFile;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::featured::FeaturedMatch;
+use crate::public::{GetPublicState, PublicMatch, PublicState};
+use crate::AppState;
+
+// Stream graphics. OBS browser sources load the pages in static/overlay, which follow
+// /public/ws and pull /api/overlay on every update.
+
+const MAX_UPCOMING: usize = 5;
+// rounds shown per side of the bracket, starting from the one being played
+const MINI_ROUNDS: usize = 2;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingOverlay {
+    pub p1: String,
+    pub p2: String,
+    // None when it's waiting on a player rather than an arena
+    pub wait_minutes: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiniRound {
+    pub round: i64,
+    pub matches: Vec<PublicMatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Overlay {
+    pub tournament: String,
+    pub featured: Option<FeaturedMatch>,
+    pub upcoming: Vec<UpcomingOverlay>,
+    pub bracket: Vec<MiniRound>,
+}
+
+/// The round still being played on one side of the bracket and the ones after it.
+fn mini_rounds(bracket: &[PublicMatch], losers: bool) -> Vec<MiniRound> {
+    let mut rounds: Vec<i64> = bracket
+        .iter()
+        .map(|m| m.round)
+        .filter(|&r| (r < 0) == losers)
+        .collect();
+    rounds.sort_by_key(|r| r.abs());
+    rounds.dedup();
+    let current = rounds
+        .iter()
+        .position(|&r| {
+            bracket
+                .iter()
+                .any(|m| m.round == r && m.state != "complete")
+        })
+        .unwrap_or(rounds.len().saturating_sub(MINI_ROUNDS));
+
+    rounds
+        .into_iter()
+        .skip(current)
+        .take(MINI_ROUNDS)
+        .map(|round| MiniRound {
+            round,
+            matches: bracket
+                .iter()
+                .filter(|m| m.round == round)
+                .cloned()
+                .collect(),
+        })
+        .collect()
+}
+
+impl From<PublicState> for Overlay {
+    fn from(state: PublicState) -> Self {
+        let queued = state.queue.iter().map(|q| UpcomingOverlay {
+            p1: q.p1.clone(),
+            p2: q.p2.clone(),
+            wait_minutes: Some(q.wait_minutes),
+        });
+        let waiting = state.upcoming.iter().map(|u| UpcomingOverlay {
+            p1: u.p1.clone(),
+            p2: u.p2.clone(),
+            wait_minutes: None,
+        });
+        let mut bracket = mini_rounds(&state.bracket, false);
+        bracket.extend(mini_rounds(&state.bracket, true));
+
+        Overlay {
+            tournament: state.tournament,
+            featured: state.featured,
+            upcoming: queued.chain(waiting).take(MAX_UPCOMING).collect(),
+            bracket,
+        }
+    }
+}
+
+pub async fn overlay_route(data: web::Data<AppState>) -> HttpResponse {
+    match data.tournment.send(GetPublicState).await {
+        Ok(state) => HttpResponse::Ok().json(Overlay::from(state)),
+        Err(e) => HttpResponse::ServiceUnavailable().body(e.to_string()),
+    }
+}
+
+pub async fn overlay_page(page: web::Path<String>) -> impl Responder {
+    let file = match page.as_str() {
+        "score" => "./static/overlay/score.html",
+        "upcoming" => "./static/overlay/upcoming.html",
+        "bracket" => "./static/overlay/bracket.html",
+        _ => return Err(actix_web::error::ErrorNotFound("no such overlay")),
+    };
+    NamedFile::open_async(file).await.map_err(Into::into)
+}
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::steamid::SteamId;
+
+pub const PARTICIPANTS_FILE: &str = "participants.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Participant {
+    pub tournament: String,
+    pub steam_id: SteamId,
+    pub name: String,
+    // other names the player has shown up with in game
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// Which bracket participant is which player. Challonge participant ids are unique
+/// across tournaments so they key the whole table.
+///
+/// This is the source of truth for steamids, the participant `misc` field is only read
+/// for participants added before rustmge kept track of them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ParticipantStore {
+    participants: HashMap<u64, Participant>,
+    #[serde(skip)]
+    path: String,
+}
+
+impl ParticipantStore {
+    pub fn load(path: &str) -> Self {
+        let mut store: ParticipantStore = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                println!("could not parse {}: {}, starting fresh", path, e);
+                ParticipantStore::default()
+            }),
+            Err(_) => ParticipantStore::default(),
+        };
+        store.path = path.to_string();
+        store
+    }
+
+    pub fn save(&self) {
+        let text = serde_json::to_string_pretty(self).unwrap();
+        if let Err(e) = std::fs::write(&self.path, text) {
+            println!("could not write {}: {}", self.path, e);
+        }
+    }
+
+    pub fn insert(&mut self, participant_id: u64, tournament: &str, steam_id: SteamId, name: &str) {
+        let aliases = self
+            .participants
+            .values()
+            .find(|p| p.steam_id == steam_id)
+            .map(|p| p.aliases.clone())
+            .unwrap_or_default();
+        self.participants.insert(
+            participant_id,
+            Participant {
+                tournament: tournament.to_string(),
+                steam_id,
+                name: name.to_string(),
+                aliases,
+            },
+        );
+        self.save();
+    }
+
+    pub fn get(&self, participant_id: u64) -> Option<&Participant> {
+        self.participants.get(&participant_id)
+    }
+
+    /// Remembers the name a player is currently using if it's a new one.
+    pub fn seen_as(&mut self, steam_id: &SteamId, name: &str) {
+        let mut changed = false;
+        for p in self.participants.values_mut() {
+            if p.steam_id == *steam_id && p.name != name && !p.aliases.iter().any(|a| a == name) {
+                p.aliases.push(name.to_string());
+                changed = true;
+            }
+        }
+        if changed {
+            self.save();
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use actix::prelude::*;
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use serde::{Deserialize, Serialize};
+
+use crate::featured::FeaturedMatch;
+use crate::server::{Phase, Tournament};
+use crate::AppState;
+
+// Read-only view of the tournament for players and viewers. Only names go out here,
+// never steamids or anything the game servers send us.
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicMatch {
+    pub id: u64,
+    pub round: i64,
+    pub state: String,
+    pub p1: Option<String>,
+    pub p2: Option<String>,
+    pub winner: Option<String>,
+    pub scores: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveArena {
+    pub arena: i32,
+    pub p1: String,
+    pub p2: String,
+    pub p1_score: i32,
+    pub p2_score: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingMatch {
+    pub p1: String,
+    pub p2: String,
+}
+
+/// A match waiting for a free arena.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedMatch {
+    pub match_id: u64,
+    pub position: usize,
+    pub p1: String,
+    pub p2: String,
+    pub wait_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Standing {
+    pub rank: u64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, MessageResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicState {
+    pub tournament: String,
+    pub challonge_url: String,
+    pub bracket: Vec<PublicMatch>,
+    pub arenas: Vec<LiveArena>,
+    pub upcoming: Vec<UpcomingMatch>,
+    pub queue: Vec<QueuedMatch>,
+    pub featured: Option<FeaturedMatch>,
+    pub standings: Vec<Standing>,
+    pub phase: Phase,
+}
+
+#[derive(Message)]
+#[rtype(result = "PublicState")]
+pub struct GetPublicState;
+
+/// Serialized update pushed to read-only subscribers.
+#[derive(Message, Clone)]
+#[rtype(result = "()")]
+pub struct PublicUpdate(pub String);
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Subscribe {
+    pub addr: Recipient<PublicUpdate>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Unsubscribe {
+    pub addr: Recipient<PublicUpdate>,
+}
+
+pub struct PublicWs {
+    addr: Addr<Tournament>,
+}
+
+impl Actor for PublicWs {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.addr.do_send(Subscribe {
+            addr: ctx.address().recipient(),
+        });
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.addr.do_send(Unsubscribe {
+            addr: ctx.address().recipient(),
+        });
+    }
+}
+
+impl Handler<PublicUpdate> for PublicWs {
+    type Result = ();
+
+    fn handle(&mut self, msg: PublicUpdate, ctx: &mut Self::Context) {
+        ctx.text(msg.0);
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for PublicWs {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        // read-only, anything the client sends is ignored
+        match msg {
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            _ => (),
+        }
+    }
+}
+
+pub async fn public_ws_route(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    stream: web::Payload,
+) -> Result<HttpResponse, Error> {
+    ws::start(
+        PublicWs {
+            addr: data.tournment.clone(),
+        },
+        &req,
+        stream,
+    )
+}
+
+pub async fn public_state_route(data: web::Data<AppState>) -> HttpResponse {
+    match data.tournment.send(GetPublicState).await {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(e) => HttpResponse::ServiceUnavailable().body(e.to_string()),
+    }
+}
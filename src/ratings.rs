@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::steamid::SteamId;
+use crate::AppState;
+
+pub const RATINGS_FILE: &str = "ratings.json";
+pub const DEFAULT_RATING: f64 = 1500.0;
+// elo k-factor, new players move faster until they're out of provisional
+const K: f64 = 32.0;
+const PROVISIONAL_K: f64 = 48.0;
+const PROVISIONAL_GAMES: u32 = 10;
+const MAX_HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingChange {
+    // None for matches that weren't in the bracket
+    pub match_id: Option<u64>,
+    pub time: String,
+    pub opponent: String,
+    pub won: bool,
+    pub delta: f64,
+    pub rating: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlayerRating {
+    pub name: String,
+    pub rating: f64,
+    // set by an admin, wins over the computed rating for seeding
+    pub override_rating: Option<f64>,
+    pub wins: u32,
+    pub losses: u32,
+    pub history: Vec<RatingChange>,
+}
+
+impl PlayerRating {
+    fn new(name: &str) -> Self {
+        PlayerRating {
+            name: name.to_string(),
+            rating: DEFAULT_RATING,
+            ..Default::default()
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses
+    }
+
+    pub fn provisional(&self) -> bool {
+        self.games() < PROVISIONAL_GAMES
+    }
+
+    fn k(&self) -> f64 {
+        if self.provisional() {
+            PROVISIONAL_K
+        } else {
+            K
+        }
+    }
+
+    fn push_history(&mut self, change: RatingChange) {
+        if self.history.len() == MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(change);
+    }
+}
+
+/// Ratings per steamid, kept on disk between tournaments.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RatingStore {
+    players: HashMap<SteamId, PlayerRating>,
+    #[serde(skip)]
+    path: String,
+}
+
+impl RatingStore {
+    pub fn load(path: &str) -> Self {
+        let mut store: RatingStore = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                println!("could not parse {}: {}, starting fresh", path, e);
+                RatingStore::default()
+            }),
+            Err(_) => RatingStore::default(),
+        };
+        store.path = path.to_string();
+        store
+    }
+
+    pub fn save(&self) {
+        let text = serde_json::to_string_pretty(self).unwrap();
+        if let Err(e) = std::fs::write(&self.path, text) {
+            println!("could not write {}: {}", self.path, e);
+        }
+    }
+
+    pub fn get(&self, steam_id: &SteamId) -> Option<&PlayerRating> {
+        self.players.get(steam_id)
+    }
+
+    /// The rating used for seeding.
+    pub fn rating(&self, steam_id: &SteamId) -> f64 {
+        match self.players.get(steam_id) {
+            Some(p) => p.override_rating.unwrap_or(p.rating),
+            None => DEFAULT_RATING,
+        }
+    }
+
+    fn player(&mut self, steam_id: &SteamId, name: &str) -> &mut PlayerRating {
+        let player = self
+            .players
+            .entry(*steam_id)
+            .or_insert_with(|| PlayerRating::new(name));
+        if player.name != name && name != "unknown" {
+            player.name = name.to_string();
+        }
+        player
+    }
+
+    pub fn set_override(&mut self, steam_id: &SteamId, name: &str, rating: Option<f64>) {
+        self.player(steam_id, name).override_rating = rating;
+        self.save();
+    }
+
+    /// Elo update for one match. `margin` is the frag difference when the arena reported
+    /// scores, a bigger win moves the ratings a bit further.
+    pub fn record_result(
+        &mut self,
+        match_id: Option<u64>,
+        (winner, winner_name): (&SteamId, &str),
+        (loser, loser_name): (&SteamId, &str),
+        margin: Option<i32>,
+    ) {
+        let w = self.player(winner, winner_name).clone();
+        let l = self.player(loser, loser_name).clone();
+
+        let expected = 1.0 / (1.0 + 10f64.powf((l.rating - w.rating) / 400.0));
+        let weight = 1.0 + (margin.unwrap_or(0).max(0) as f64).ln_1p() / 4.0;
+        let winner_delta = w.k() * weight * (1.0 - expected);
+        let loser_delta = -l.k() * weight * (1.0 - expected);
+
+        let time = chrono::Local::now().to_rfc3339();
+        for (steam_id, opponent, won, delta) in [
+            (winner, &l.name, true, winner_delta),
+            (loser, &w.name, false, loser_delta),
+        ] {
+            let player = self.players.get_mut(steam_id).unwrap();
+            player.rating += delta;
+            if won {
+                player.wins += 1;
+            } else {
+                player.losses += 1;
+            }
+            let rating = player.rating;
+            player.push_history(RatingChange {
+                match_id,
+                time: time.clone(),
+                opponent: opponent.clone(),
+                won,
+                delta,
+                rating,
+            });
+        }
+        self.save();
+    }
+
+    /// Takes back whatever a bracket match did to the ratings, for reopened or corrected
+    /// results.
+    pub fn revert(&mut self, match_id: u64) {
+        let mut changed = false;
+        for player in self.players.values_mut() {
+            let Some(i) = player
+                .history
+                .iter()
+                .rposition(|c| c.match_id == Some(match_id))
+            else {
+                continue;
+            };
+            let change = player.history.remove(i);
+            player.rating -= change.delta;
+            if change.won {
+                player.wins -= 1;
+            } else {
+                player.losses -= 1;
+            }
+            changed = true;
+        }
+        if changed {
+            self.save();
+        }
+    }
+
+    pub fn leaderboard(&self) -> Vec<LeaderboardEntry> {
+        let mut players: Vec<&PlayerRating> =
+            self.players.values().filter(|p| p.games() > 0).collect();
+        players.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        players
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| LeaderboardEntry {
+                rank: i + 1,
+                name: p.name.clone(),
+                rating: p.rating.round(),
+                wins: p.wins,
+                losses: p.losses,
+                provisional: p.provisional(),
+            })
+            .collect()
+    }
+}
+
+// names only, like the rest of the public api
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub name: String,
+    pub rating: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub provisional: bool,
+}
+
+#[derive(Message)]
+#[rtype(result = "Vec<LeaderboardEntry>")]
+pub struct GetLeaderboard;
+
+pub async fn leaderboard_route(data: web::Data<AppState>) -> HttpResponse {
+    match data.tournment.send(GetLeaderboard).await {
+        Ok(leaderboard) => HttpResponse::Ok().json(leaderboard),
+        Err(e) => HttpResponse::ServiceUnavailable().body(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challonge::testing::steam_id;
+
+    fn play(store: &mut RatingStore, match_id: u64, winner: u64, loser: u64, margin: Option<i32>) {
+        store.record_result(
+            Some(match_id),
+            (&steam_id(winner), &format!("p{}", winner)),
+            (&steam_id(loser), &format!("p{}", loser)),
+            margin,
+        );
+    }
+
+    #[test]
+    fn even_match_moves_half_k() {
+        let mut store = RatingStore::default();
+        play(&mut store, 1, 1, 2, None);
+        assert_eq!(
+            store.rating(&steam_id(1)),
+            DEFAULT_RATING + PROVISIONAL_K / 2.0
+        );
+        assert_eq!(
+            store.rating(&steam_id(2)),
+            DEFAULT_RATING - PROVISIONAL_K / 2.0
+        );
+        let winner = store.get(&steam_id(1)).unwrap();
+        assert_eq!((winner.wins, winner.losses), (1, 0));
+        assert_eq!(winner.history[0].opponent, "p2");
+    }
+
+    #[test]
+    fn bigger_margin_moves_further() {
+        let mut close = RatingStore::default();
+        play(&mut close, 1, 1, 2, Some(1));
+        let mut blowout = RatingStore::default();
+        play(&mut blowout, 1, 1, 2, Some(20));
+        assert!(blowout.rating(&steam_id(1)) > close.rating(&steam_id(1)));
+        assert!(close.rating(&steam_id(1)) > DEFAULT_RATING + PROVISIONAL_K / 2.0);
+    }
+
+    #[test]
+    fn upsets_move_further() {
+        let mut store = RatingStore::default();
+        play(&mut store, 1, 1, 2, None);
+        let before = store.rating(&steam_id(2));
+        // the lower rated player wins
+        play(&mut store, 2, 2, 1, None);
+        assert!(store.rating(&steam_id(2)) - before > PROVISIONAL_K / 2.0);
+    }
+
+    #[test]
+    fn k_drops_after_provisional() {
+        let mut store = RatingStore::default();
+        for i in 0..PROVISIONAL_GAMES as u64 {
+            play(&mut store, i, 1, 2, None);
+            play(&mut store, i + 100, 2, 1, None);
+        }
+        assert!(!store.get(&steam_id(1)).unwrap().provisional());
+        assert_eq!(store.get(&steam_id(1)).unwrap().k(), K);
+    }
+
+    #[test]
+    fn revert_takes_back_only_that_match() {
+        let mut store = RatingStore::default();
+        play(&mut store, 1, 1, 2, None);
+        let (one, two) = (store.rating(&steam_id(1)), store.rating(&steam_id(2)));
+        play(&mut store, 2, 2, 1, Some(5));
+        store.revert(2);
+        assert_eq!(store.rating(&steam_id(1)), one);
+        assert_eq!(store.rating(&steam_id(2)), two);
+        let p2 = store.get(&steam_id(2)).unwrap();
+        assert_eq!((p2.wins, p2.losses), (0, 1));
+        assert_eq!(p2.history.len(), 1);
+        // unknown matches change nothing
+        store.revert(99);
+        assert_eq!(store.rating(&steam_id(1)), one);
+    }
+
+    #[test]
+    fn override_wins_for_seeding() {
+        let mut store = RatingStore::default();
+        play(&mut store, 1, 1, 2, None);
+        store.set_override(&steam_id(2), "p2", Some(2000.0));
+        assert_eq!(store.rating(&steam_id(2)), 2000.0);
+        store.set_override(&steam_id(2), "p2", None);
+        assert_eq!(
+            store.rating(&steam_id(2)),
+            DEFAULT_RATING - PROVISIONAL_K / 2.0
+        );
+        assert_eq!(store.rating(&steam_id(3)), DEFAULT_RATING);
+    }
+
+    #[test]
+    fn leaderboard_skips_players_without_games() {
+        let mut store = RatingStore::default();
+        store.set_override(&steam_id(3), "p3", Some(1800.0));
+        play(&mut store, 1, 1, 2, None);
+        let board = store.leaderboard();
+        let names: Vec<&str> = board.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["p1", "p2"]);
+        assert_eq!(board[0].rank, 1);
+        assert!(board[0].provisional);
+    }
+}
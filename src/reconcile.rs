@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::challonge::{Entrant, Match};
+use crate::steamid::SteamId;
+
+// Finds where our idea of the tournament no longer matches challonge, usually because
+// an admin fixed something on the website.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    // a result we reported is gone from the bracket
+    ResultRemoved {
+        match_id: u64,
+    },
+    ResultReopened {
+        match_id: u64,
+    },
+    WinnerChanged {
+        match_id: u64,
+        winner: SteamId,
+    },
+    ArenaMatchRemoved {
+        arena: usize,
+        match_id: u64,
+    },
+    ArenaMatchClosed {
+        arena: usize,
+        match_id: u64,
+        state: String,
+    },
+    ArenaPlayersChanged {
+        arena: usize,
+        match_id: u64,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::ResultRemoved { match_id } => {
+                write!(f, "match {} was removed from the bracket", match_id)
+            }
+            Discrepancy::ResultReopened { match_id } => {
+                write!(f, "match {} was reopened on challonge", match_id)
+            }
+            Discrepancy::WinnerChanged { match_id, winner } => {
+                write!(f, "match {} was changed to a win for {}", match_id, winner)
+            }
+            Discrepancy::ArenaMatchRemoved { arena, match_id } => write!(
+                f,
+                "match {} in arena {} was removed from the bracket",
+                match_id, arena
+            ),
+            Discrepancy::ArenaMatchClosed {
+                arena,
+                match_id,
+                state,
+            } => write!(
+                f,
+                "match {} in arena {} is {} on challonge",
+                match_id, arena, state
+            ),
+            Discrepancy::ArenaPlayersChanged { arena, match_id } => write!(
+                f,
+                "players of match {} in arena {} were changed on challonge",
+                match_id, arena
+            ),
+        }
+    }
+}
+
+/// `results` is (match id, winner) for everything we reported, `arenas` is (arena, match
+/// id, p1, p2) for every bracket match being played.
+pub fn reconcile(
+    matches: &[Match],
+    entrants: &HashMap<u64, Entrant>,
+    results: &[(u64, SteamId)],
+    arenas: &[(usize, u64, SteamId, SteamId)],
+) -> Vec<Discrepancy> {
+    let steam_id = |pid: Option<u64>| pid.and_then(|pid| entrants.get(&pid)?.steam_id);
+    let mut found = vec![];
+
+    for &(match_id, winner) in results {
+        let Some(m) = matches.iter().find(|m| m.id == match_id) else {
+            found.push(Discrepancy::ResultRemoved { match_id });
+            continue;
+        };
+        match steam_id(m.winner_id) {
+            None if m.winner_id.is_none() => found.push(Discrepancy::ResultReopened { match_id }),
+            Some(now) if now != winner => found.push(Discrepancy::WinnerChanged {
+                match_id,
+                winner: now,
+            }),
+            _ => {}
+        }
+    }
+
+    for &(arena, match_id, p1, p2) in arenas {
+        let Some(m) = matches.iter().find(|m| m.id == match_id) else {
+            found.push(Discrepancy::ArenaMatchRemoved { arena, match_id });
+            continue;
+        };
+        if m.state != "open" {
+            found.push(Discrepancy::ArenaMatchClosed {
+                arena,
+                match_id,
+                state: m.state.clone(),
+            });
+            continue;
+        }
+        let mut players = [steam_id(m.player1_id), steam_id(m.player2_id)];
+        players.sort();
+        let mut ours = [Some(p1), Some(p2)];
+        ours.sort();
+        if players != ours {
+            found.push(Discrepancy::ArenaPlayersChanged { arena, match_id });
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challonge::testing::{entrants, steam_id};
+
+    fn check(
+        matches: &[Match],
+        results: &[(u64, SteamId)],
+        arenas: &[(usize, u64, SteamId, SteamId)],
+    ) -> Vec<Discrepancy> {
+        reconcile(matches, &entrants(4), results, arenas)
+    }
+
+    #[test]
+    fn in_sync() {
+        let matches = [
+            Match::between(1, 1, 1, 2, Some(1)),
+            Match::between(2, 1, 3, 4, None),
+        ];
+        // players in either order
+        assert!(check(
+            &matches,
+            &[(1, steam_id(1))],
+            &[(0, 2, steam_id(4), steam_id(3))]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn results_changed_on_challonge() {
+        let matches = [
+            Match::between(1, 1, 1, 2, None),
+            Match::between(2, 1, 3, 4, Some(4)),
+        ];
+        let found = check(
+            &matches,
+            &[(1, steam_id(1)), (2, steam_id(3)), (3, steam_id(1))],
+            &[],
+        );
+        assert_eq!(
+            found,
+            vec![
+                Discrepancy::ResultReopened { match_id: 1 },
+                Discrepancy::WinnerChanged {
+                    match_id: 2,
+                    winner: steam_id(4)
+                },
+                Discrepancy::ResultRemoved { match_id: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn arenas_changed_on_challonge() {
+        let matches = [
+            Match::between(1, 1, 1, 2, Some(2)),
+            Match::between(2, 1, 3, 4, None),
+        ];
+        let arenas = [
+            (0, 1, steam_id(1), steam_id(2)),
+            (1, 2, steam_id(1), steam_id(4)),
+            (2, 3, steam_id(1), steam_id(2)),
+        ];
+        assert_eq!(
+            check(&matches, &[], &arenas),
+            vec![
+                Discrepancy::ArenaMatchClosed {
+                    arena: 0,
+                    match_id: 1,
+                    state: "complete".to_string()
+                },
+                Discrepancy::ArenaPlayersChanged {
+                    arena: 1,
+                    match_id: 2
+                },
+                Discrepancy::ArenaMatchRemoved {
+                    arena: 2,
+                    match_id: 3
+                },
+            ]
+        );
+    }
+}
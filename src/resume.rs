@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::server::{ArenaMatch, HeldResult, Phase};
+
+pub const ARENAS_FILE: &str = "arenas.json";
+
+/// What was being played where, saved on every change so a restart mid-cup can pick
+/// the arenas back up. Challonge only knows a match is underway, not where.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedArenas {
+    pub tournament: String,
+    pub arenas: Vec<Option<ArenaMatch>>,
+    // results that came in while paused, their arenas are already free
+    #[serde(default)]
+    pub held: Vec<HeldResult>,
+    // so a restart doesn't undo a pause or stop, None for older files
+    #[serde(default)]
+    pub phase: Option<Phase>,
+}
+
+impl SavedArenas {
+    /// What was saved if it belongs to `tournament`.
+    pub fn load(tournament: &str) -> Option<SavedArenas> {
+        let text = std::fs::read_to_string(ARENAS_FILE).ok()?;
+        let saved: SavedArenas = serde_json::from_str(&text)
+            .inspect_err(|e| println!("could not parse {}: {}", ARENAS_FILE, e))
+            .ok()?;
+        (saved.tournament == tournament).then_some(saved)
+    }
+
+    pub fn save(
+        tournament: &str,
+        phase: Phase,
+        arenas: &[Option<ArenaMatch>],
+        held: &[HeldResult],
+    ) {
+        let saved = SavedArenas {
+            tournament: tournament.to_string(),
+            arenas: arenas.to_vec(),
+            held: held.to_vec(),
+            phase: Some(phase),
+        };
+        let text = serde_json::to_string(&saved).unwrap();
+        if let Err(e) = std::fs::write(ARENAS_FILE, text) {
+            println!("could not write {}: {}", ARENAS_FILE, e);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Duration as TimeDelta, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::challonge::TournamentOptions;
+
+pub const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When a template runs by itself. Times are UTC.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub weekday: Weekday,
+    pub start: NaiveTime,
+    // the tournament gets created this long before the start so players can sign up
+    pub create_hours_before: i64,
+    pub check_in_minutes_before: i64,
+    // replaces {n} in the name and url, bumped every time one is created
+    pub next_number: u64,
+    // the instance we created and haven't started yet
+    #[serde(default)]
+    pub current: Option<ScheduledRun>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledRun {
+    pub url: String,
+    pub start_at: DateTime<Utc>,
+}
+
+impl ScheduledRun {
+    pub fn check_in_at(&self, schedule: &Schedule) -> DateTime<Utc> {
+        self.start_at - TimeDelta::minutes(schedule.check_in_minutes_before)
+    }
+}
+
+impl Schedule {
+    /// The first start at or after `now`.
+    pub fn next_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let days = (7 + self.weekday.num_days_from_monday() as i64
+            - now.weekday().num_days_from_monday() as i64)
+            % 7;
+        let start = (now.date_naive() + TimeDelta::days(days))
+            .and_time(self.start)
+            .and_utc();
+        if start < now {
+            start + TimeDelta::weeks(1)
+        } else {
+            start
+        }
+    }
+
+    /// Every instance needs its own url, challonge won't create the same one twice.
+    pub fn check(&self, options: &TournamentOptions) -> Result<(), String> {
+        if !options.url.contains("{n}") {
+            return Err(format!(
+                "scheduled url {} needs {{n}} to number the instances",
+                options.url
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn create_at(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        start - TimeDelta::hours(self.create_hours_before)
+    }
+
+    /// Options for the next instance, the caller bumps `next_number` once it exists.
+    pub fn instance(&self, options: &TournamentOptions, start: DateTime<Utc>) -> TournamentOptions {
+        let n = self.next_number.to_string();
+        TournamentOptions {
+            name: options.name.replace("{n}", &n),
+            url: options.url.replace("{n}", &n),
+            start_at: Some(start),
+            ..options.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challonge::{Format, GrandFinals, Ranking};
+
+    // wednesdays at 19:00
+    fn wednesdays() -> Schedule {
+        Schedule {
+            weekday: Weekday::Wed,
+            start: NaiveTime::from_hms_opt(19, 0, 0).unwrap(),
+            create_hours_before: 48,
+            check_in_minutes_before: 30,
+            next_number: 1,
+            current: None,
+        }
+    }
+
+    fn options(url: &str) -> TournamentOptions {
+        TournamentOptions {
+            name: "weekly cup #{n}".to_string(),
+            url: url.to_string(),
+            description: String::new(),
+            format: Format::DoubleElimination,
+            hold_third_place_match: false,
+            grand_finals: GrandFinals::TwoChances,
+            ranked_by: Ranking::MatchWins,
+            signup_cap: 64,
+            swiss_rounds: 0,
+            start_at: None,
+            private: false,
+        }
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn next_start_later_in_the_week() {
+        // a monday
+        let start = wednesdays().next_start(at("2024-01-01T12:00:00Z"));
+        assert_eq!(start, at("2024-01-03T19:00:00Z"));
+    }
+
+    #[test]
+    fn next_start_later_the_same_day() {
+        let start = wednesdays().next_start(at("2024-01-03T18:59:00Z"));
+        assert_eq!(start, at("2024-01-03T19:00:00Z"));
+        // exactly at the start still counts
+        let start = wednesdays().next_start(at("2024-01-03T19:00:00Z"));
+        assert_eq!(start, at("2024-01-03T19:00:00Z"));
+    }
+
+    #[test]
+    fn next_start_after_this_weeks_has_passed() {
+        let start = wednesdays().next_start(at("2024-01-03T19:01:00Z"));
+        assert_eq!(start, at("2024-01-10T19:00:00Z"));
+        // a thursday
+        let start = wednesdays().next_start(at("2024-01-04T08:00:00Z"));
+        assert_eq!(start, at("2024-01-10T19:00:00Z"));
+    }
+
+    #[test]
+    fn create_and_check_in_times() {
+        let schedule = wednesdays();
+        let start = at("2024-01-03T19:00:00Z");
+        assert_eq!(schedule.create_at(start), at("2024-01-01T19:00:00Z"));
+        let run = ScheduledRun {
+            url: "mge_1".to_string(),
+            start_at: start,
+        };
+        assert_eq!(run.check_in_at(&schedule), at("2024-01-03T18:30:00Z"));
+    }
+
+    #[test]
+    fn instance_numbers_name_and_url() {
+        let mut schedule = wednesdays();
+        schedule.next_number = 7;
+        let start = at("2024-01-03T19:00:00Z");
+        let instance = schedule.instance(&options("mge_weekly_{n}"), start);
+        assert_eq!(instance.name, "weekly cup #7");
+        assert_eq!(instance.url, "mge_weekly_7");
+        assert_eq!(instance.start_at, Some(start));
+        assert_eq!(instance.signup_cap, 64);
+    }
+
+    #[test]
+    fn url_has_to_be_numbered() {
+        assert!(wednesdays().check(&options("mge_weekly_{n}")).is_ok());
+        assert!(wednesdays().check(&options("mge_weekly")).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+// Standard bracket seeding, where 1 meets the lowest seed and 1 and 2 can only meet in
+// the final.
+
+/// Seeds in bracket order for a bracket of `size` slots (a power of two).
+pub fn bracket_order(size: usize) -> Vec<usize> {
+    let mut order = vec![1];
+    while order.len() < size {
+        let n = order.len() * 2 + 1;
+        order = order.iter().flat_map(|&s| [s, n - s]).collect();
+    }
+    order
+}
+
+/// First round pairings for `players` already sorted best first. A `None` opponent is a
+/// bye.
+pub fn first_round<T: Clone>(players: &[T]) -> Vec<(T, Option<T>)> {
+    if players.is_empty() {
+        return vec![];
+    }
+    // a lone player still gets a slot to have a bye against
+    let size = players.len().next_power_of_two().max(2);
+    let seeded = |seed: usize| players.get(seed - 1).cloned();
+    bracket_order(size)
+        .chunks(2)
+        .filter_map(|pair| match (seeded(pair[0]), seeded(pair[1])) {
+            (Some(a), b) => Some((a, b)),
+            (None, Some(b)) => Some((b, None)),
+            (None, None) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bracket_order_sizes() {
+        assert_eq!(bracket_order(0), vec![1]);
+        assert_eq!(bracket_order(1), vec![1]);
+        assert_eq!(bracket_order(2), vec![1, 2]);
+        assert_eq!(bracket_order(4), vec![1, 4, 2, 3]);
+        assert_eq!(bracket_order(8), vec![1, 8, 4, 5, 2, 7, 3, 6]);
+    }
+
+    #[test]
+    fn first_round_no_players() {
+        assert!(first_round::<u32>(&[]).is_empty());
+    }
+
+    #[test]
+    fn first_round_one_player_gets_a_bye() {
+        assert_eq!(first_round(&[1]), vec![(1, None)]);
+    }
+
+    #[test]
+    fn first_round_two_players() {
+        assert_eq!(first_round(&[1, 2]), vec![(1, Some(2))]);
+    }
+
+    #[test]
+    fn first_round_three_players() {
+        assert_eq!(first_round(&[1, 2, 3]), vec![(1, None), (2, Some(3))]);
+    }
+
+    #[test]
+    fn first_round_four_players() {
+        assert_eq!(first_round(&[1, 2, 3, 4]), vec![(1, Some(4)), (2, Some(3))]);
+    }
+
+    #[test]
+    fn first_round_five_players() {
+        assert_eq!(
+            first_round(&[1, 2, 3, 4, 5]),
+            vec![(1, None), (4, Some(5)), (2, None), (3, None)]
+        );
+    }
+}
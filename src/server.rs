@@ -1,99 +1,1891 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, VecDeque};
 
-use crate::{challonge::SUBDOMAIN, ForwardMessage, ServerWs};
+use crate::archive::{Archive, TOP_PLACEMENTS};
+use crate::arenas::{ArenaRequest, ArenaSelection, FixedPriority};
+use crate::bracket::{BracketCache, REFRESH_INTERVAL};
+use crate::challonge::NamedPlayer;
+use crate::challonge::{Entrant, Match, CHECK_IN_DURATION, SUBDOMAIN};
+use crate::dispatch::{BracketGraph, CriticalPath, DispatchPolicy};
+use crate::events::{EventLog, EventPlayer, SpectatorEvent, SubscribeEvents, UnsubscribeEvents};
+use crate::featured::{FeaturedMatch, GetFeatured};
+use crate::participants::{ParticipantStore, PARTICIPANTS_FILE};
+use crate::public::{
+    GetPublicState, LiveArena, PublicMatch, PublicState, PublicUpdate, QueuedMatch, Standing,
+    Subscribe, Unsubscribe, UpcomingMatch,
+};
+use crate::ratings::{GetLeaderboard, LeaderboardEntry, RatingStore, DEFAULT_RATING, RATINGS_FILE};
+use crate::reconcile::Discrepancy;
+use crate::resume::SavedArenas;
+use crate::scheduler::{ScheduledRun, SCHEDULE_INTERVAL};
+use crate::steamid::SteamId;
+use crate::templates::{Template, TemplateStore, TEMPLATES_FILE};
+use crate::{ForwardMessage, ServerWs};
 use actix::prelude::*;
+use chrono::{DateTime, Duration as TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
 
 const NUM_ARENAS: usize = 16;
+// url of the tournament we're running, so a restart comes back to it
+const TOURNAMENT_FILE: &str = "tournament.txt";
+// what the dashboard has to send as its api key, no admins get in without it
+const ADMIN_KEY_FILE: &str = "admin_key.txt";
+const MAX_RECENT_ERRORS: usize = 20;
+// wait estimates use the average of this many recent matches
+const RECENT_MATCHES: usize = 20;
+pub const DEFAULT_MATCH_MINUTES: f64 = 8.0;
+// how long players get between matches unless an admin changes it
+const DEFAULT_MIN_REST_SECS: i64 = 90;
+// how often we look for players coming off their rest
+const REST_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
 
-pub struct MatchDetails {
-    arena_id: String,
-    p1_id: String,
-    p2_id: String,
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArenaMatch {
+    // None for matches assigned by hand from the dashboard
+    match_id: Option<u64>,
+    p1_id: SteamId,
+    p2_id: SteamId,
+    p1_score: i32,
+    p2_score: i32,
+    #[serde(default = "Utc::now")]
+    assigned_at: DateTime<Utc>,
+    // server_num of the game server playing it, the first one to tell us about it
+    #[serde(default)]
+    server: Option<String>,
+}
+
+impl ArenaMatch {
+    pub fn new(match_id: Option<u64>, p1_id: SteamId, p2_id: SteamId) -> Self {
+        ArenaMatch {
+            match_id,
+            p1_id,
+            p2_id,
+            p1_score: 0,
+            p2_score: 0,
+            assigned_at: Utc::now(),
+            server: None,
+        }
+    }
+
+    pub fn contains(&self, steam_id: &SteamId) -> bool {
+        self.p1_id == *steam_id || self.p2_id == *steam_id
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    server_num: String,
+    server_host: String,
+    server_port: String,
+    stv_port: String,
+}
+
+pub struct GameServer {
+    addr: actix::Addr<ServerWs>,
+    info: ServerInfo,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArenaView {
+    arena: i32,
+    #[serde(rename = "match")]
+    mtch: Option<ArenaMatch>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingMatch {
+    match_id: u64,
+    p1_id: SteamId,
+    p2_id: SteamId,
+}
+
+// a match a game server called off, it stays out of dispatch until an admin retries it
+// or reports a forfeit
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledMatch {
+    match_id: u64,
+    p1_id: SteamId,
+    p2_id: SteamId,
+    // who didn't show up, as the game server saw it
+    delinquents: Vec<SteamId>,
+}
+
+// a result that came in while paused, reported on resume
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeldResult {
+    arena: i32,
+    // the bracket match the arena was playing, None for one assigned by hand
+    #[serde(default)]
+    match_id: Option<u64>,
+    winner: SteamId,
+    loser: SteamId,
+    margin: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundSelection {
+    round: i64,
+    selection: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resting {
+    steam_id: SteamId,
+    seconds_left: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedResult {
+    match_id: u64,
+    arena: i32,
+    winner: SteamId,
+    loser: SteamId,
+    disputed: Option<String>,
+}
+
+/// Where the tournament is in its lifecycle. Every transition past CheckingIn is an
+/// explicit admin action.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Phase {
+    // UsersInServer registers players
+    CollectingRoster,
+    // still collecting, but players also have to check in
+    CheckingIn,
+    // nobody else gets in, seeds can still change
+    RosterLocked,
+    Running,
+    // matches being played can finish, nothing new goes out and results wait for resume
+    Paused,
+    // TournamentStop called everything back, TournamentStart picks the bracket up again
+    Stopped,
+    Finished,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Registration {
+    steam_id: Option<SteamId>,
+    name: String,
+    checked_in: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Seed {
+    seed: usize,
+    steam_id: Option<SteamId>,
+    name: String,
+    rating: f64,
+    overridden: bool,
+}
+
+// first round of the bracket the current seeds would produce, p2 None is a bye
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pairing {
+    p1: String,
+    p2: Option<String>,
+}
+
+/// Everything the admin page renders, pushed whenever the tournament changes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dashboard {
+    tournament: String,
+    arenas: Vec<ArenaView>,
+    pending: Vec<PendingMatch>,
+    underway: Vec<PendingMatch>,
+    queue: Vec<QueuedMatch>,
+    held: Vec<HeldResult>,
+    cancelled: Vec<CancelledMatch>,
+    featured: Option<FeaturedMatch>,
+    resting: Vec<Resting>,
+    min_rest_secs: i64,
+    arena_selection: String,
+    round_arena_selection: Vec<RoundSelection>,
+    arena_selections: Vec<String>,
+    results: Vec<ReportedResult>,
+    servers: Vec<ServerInfo>,
+    roster: Vec<crate::Player>,
+    phase: Phase,
+    registered: Vec<Registration>,
+    seeding: Vec<Seed>,
+    pairings: Vec<Pairing>,
+    templates: Vec<Template>,
+    dispatch_policy: String,
+    dispatch_policies: Vec<String>,
+    errors: Vec<String>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Disconnect {
+    pub addr: actix::Addr<ServerWs>,
 }
 
 pub struct Tournament {
-    admin: Option<actix::Addr<ServerWs>>,
-    servers: Vec<actix::Addr<ServerWs>>,
+    admins: Vec<actix::Addr<ServerWs>>,
+    servers: Vec<GameServer>,
     players: Vec<crate::Player>,
-    arena_to_match: Vec<Option<HashSet<String>>>,
+    phase: Phase,
+    arena_to_match: Vec<Option<ArenaMatch>>,
     arena_priority_order: Vec<i32>,
+    pending: Vec<PendingMatch>,
+    // flagged underway on challonge but we don't know the arena, e.g. after a restart
+    underway: Vec<PendingMatch>,
+    // ready to play but every arena is busy, handed out in this order
+    queue: Vec<PendingMatch>,
+    // how long recent bracket matches took, for the queue's wait estimates
+    match_minutes: VecDeque<f64>,
+    // picks which ready match gets the next free arena
+    dispatch: Box<dyn DispatchPolicy>,
+    // when each player last finished a match, nobody gets sent again before min_rest
+    finished_at: HashMap<SteamId, DateTime<Utc>>,
+    min_rest: TimeDelta,
+    // where matches go, a round can have its own
+    arena_selection: Box<dyn ArenaSelection>,
+    round_arena_selection: HashMap<i64, Box<dyn ArenaSelection>>,
+    arena_used: HashMap<usize, DateTime<Utc>>,
+    last_arena: HashMap<SteamId, usize>,
+    held: Vec<HeldResult>,
+    cancelled: Vec<CancelledMatch>,
+    // the match for the stream, played in the showcase arena when it's free
+    featured: Option<u64>,
+    featured_pinned: bool,
+    results: Vec<ReportedResult>,
+    errors: VecDeque<String>,
+    subscribers: Vec<Recipient<PublicUpdate>>,
+    events: EventLog,
+    event_subscribers: Vec<Recipient<PublicUpdate>>,
+    bracket: BracketCache,
+    ratings: RatingStore,
+    participants: ParticipantStore,
+    templates: TemplateStore,
     c: Challonge,
     tc: challonge::Tournament,
 }
 
-pub fn get_open_arena(
-    arena_to_match: &Vec<Option<HashSet<String>>>,
-    arena_priority_order: &Vec<i32>,
+use challonge::Challonge;
+
+// who sent a message, as far as results go
+enum Sender {
+    Admin,
+    Server(String),
+    Unknown,
+}
+
+/// A result has to name the two players we put in that arena and come from the
+/// server playing it, or from an admin.
+fn check_result(
+    m: Option<&ArenaMatch>,
+    arena: i32,
+    sender: &Sender,
+    winner: &SteamId,
+    loser: &SteamId,
+) -> Result<(), String> {
+    let Some(m) = m else {
+        return Err(format!("there is no match in arena {}", arena));
+    };
+    if winner == loser || !m.contains(winner) || !m.contains(loser) {
+        return Err(format!(
+            "{} and {} aren't the players in arena {}",
+            winner, loser, arena
+        ));
+    }
+    match (&m.server, sender) {
+        (_, Sender::Admin) => Ok(()),
+        (_, Sender::Unknown) => Err("results have to come from a game server".to_string()),
+        (Some(owner), Sender::Server(sender)) if owner != sender => Err(format!(
+            "arena {} is played on server {}, not {}",
+            arena, owner, sender
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Which underway match a result for an empty arena belongs to, if any.
+fn underway_claim(
+    m: Option<&ArenaMatch>,
+    underway: &[PendingMatch],
+    sender: &Sender,
+    winner: &SteamId,
+    loser: &SteamId,
 ) -> Option<usize> {
-    for &arena in arena_priority_order {
-        if arena_to_match[arena as usize].is_none() {
-            return Some(arena as usize);
-        }
+    if m.is_some() || matches!(sender, Sender::Unknown) || winner == loser {
+        return None;
     }
-    None
+    underway
+        .iter()
+        .position(|u| [u.p1_id, u.p2_id].contains(winner) && [u.p1_id, u.p2_id].contains(loser))
 }
 
-use challonge::{matches::Player, Challonge};
+fn rest_left(
+    finished_at: Option<DateTime<Utc>>,
+    min_rest: TimeDelta,
+    now: DateTime<Utc>,
+) -> TimeDelta {
+    match finished_at {
+        Some(at) => (at + min_rest - now).max(TimeDelta::zero()),
+        None => TimeDelta::zero(),
+    }
+}
+
+/// Minutes each of `queued` matches waits, given when each arena is free again and
+/// how long a match takes.
+fn queue_waits(mut free_in: Vec<f64>, average: f64, queued: usize) -> Vec<f64> {
+    if free_in.is_empty() {
+        free_in.push(0.0);
+    }
+    (0..queued)
+        .map(|_| {
+            let (slot, soonest) = free_in
+                .iter()
+                .copied()
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            free_in[slot] = soonest + average;
+            soonest
+        })
+        .collect()
+}
 
 impl Tournament {
     pub fn new(c: Challonge) -> Self {
-        let tid = challonge::TournamentId::Url(SUBDOMAIN.to_string(), "mge5".to_string());
+        let url = std::fs::read_to_string(TOURNAMENT_FILE)
+            .map(|url| url.trim().to_string())
+            .unwrap_or("mge5".to_string());
+        let tid = challonge::TournamentId::Url(SUBDOMAIN.to_string(), url);
         let tc = c
             .get_tournament(&tid, &challonge::TournamentIncludes::All)
             .unwrap();
+        let mut participants = ParticipantStore::load(PARTICIPANTS_FILE);
+        let mut bracket = BracketCache::new();
+        if let Err(e) = bracket.refresh_matches(&tc.id) {
+            println!("{}", e);
+        }
+        if let Err(e) = bracket.refresh_entrants(&c, &tc, &mut participants) {
+            println!("{}", e);
+        }
 
-        Tournament {
-            admin: None,
+        let mut tournament = Tournament {
+            admins: vec![],
             servers: vec![],
             c,
             tc,
             players: vec![],
+            phase: Phase::CollectingRoster,
             arena_to_match: vec![None; NUM_ARENAS],
             //arena_priority_order: vec![5, 6, 7, 1, 2, 3, 4, 8, 9, 10, 11, 12, 13, 14, 15, 16], //triump spire
             //arena_priority_order: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16], //triumph blands mid
             arena_priority_order: vec![5, 4, 9, 10, 2, 3, 9, 11, 12, 13, 14, 15, 16], // oighuv variety
+            pending: vec![],
+            underway: vec![],
+            queue: vec![],
+            match_minutes: VecDeque::new(),
+            dispatch: Box::new(CriticalPath),
+            finished_at: HashMap::new(),
+            min_rest: TimeDelta::seconds(DEFAULT_MIN_REST_SECS),
+            arena_selection: Box::new(FixedPriority),
+            round_arena_selection: HashMap::new(),
+            arena_used: HashMap::new(),
+            last_arena: HashMap::new(),
+            held: vec![],
+            cancelled: vec![],
+            featured: None,
+            featured_pinned: false,
+            results: vec![],
+            errors: VecDeque::new(),
+            subscribers: vec![],
+            events: EventLog::new(),
+            event_subscribers: vec![],
+            bracket,
+            ratings: RatingStore::load(RATINGS_FILE),
+            participants,
+            templates: TemplateStore::load(TEMPLATES_FILE),
+        };
+        // challonge only creates matches once the bracket has been started
+        if !tournament.bracket.matches.is_empty() {
+            tournament.resume();
+        }
+        tournament
+    }
+
+    fn open_check_in(&mut self, start_at: DateTime<Utc>) -> Result<(), String> {
+        crate::challonge::open_check_in(&self.tc, start_at)?;
+        self.phase = Phase::CheckingIn;
+        self.broadcast(MessagePayload::OpenCheckIn {});
+        Ok(())
+    }
+
+    fn lock_roster(&mut self) -> Result<(), String> {
+        if self.phase == Phase::CheckingIn {
+            crate::challonge::process_check_ins(&self.tc)?;
+        }
+        self.refresh_entrants();
+        self.roster_from_entrants();
+        self.phase = Phase::RosterLocked;
+        self.apply_seeding();
+        Ok(())
+    }
+
+    fn start_bracket(&mut self) -> Result<(), String> {
+        // challonge won't start a bracket for fewer than two
+        if self.players.len() < 2 {
+            return Err(format!(
+                "{} has {} players, not enough to start",
+                self.tc.url,
+                self.players.len()
+            ));
+        }
+        crate::challonge::start_tournament(&self.tc)?;
+        self.bracket.invalidate();
+        self.phase = Phase::Running;
+        self.emit(SpectatorEvent::TournamentStarted {
+            tournament: self.tc.name.clone(),
+        });
+        self.send_pending_matches();
+        Ok(())
+    }
+
+    /// Finishes by itself once every match in the bracket has a result and nothing is
+    /// left in an arena.
+    fn finish_if_complete(&mut self) {
+        let matches = &self.bracket.matches;
+        if self.phase != Phase::Running
+            || matches.is_empty()
+            || matches.iter().any(|m| m.state != "complete")
+            || self.arena_to_match.iter().any(|m| m.is_some())
+        {
+            return;
+        }
+        println!("every match of {} is done", self.tc.url);
+        if let Err(e) = self.finish() {
+            self.record_error(e);
+        }
+    }
+
+    /// Finalizes on challonge, tells the servers the standings so they leave tournament
+    /// mode and archives the tournament.
+    fn finish(&mut self) -> Result<(), String> {
+        // the servers can't be told it's over while challonge still has it open
+        crate::challonge::finalize_tournament(&self.tc)
+            .map_err(|e| format!("could not finalize {}: {}", self.tc.url, e))?;
+        // final ranks only show up on the participants after finalizing
+        self.refresh_entrants();
+        let placements = crate::archive::placements(&self.bracket.matches, &self.bracket.entrants);
+        Archive {
+            name: &self.tc.name,
+            url: &self.tc.url,
+            finished_at: Utc::now(),
+            placements: &placements,
+            results: &self.results,
+        }
+        .save();
+
+        let top: Vec<_> = placements.into_iter().take(TOP_PLACEMENTS).collect();
+        self.broadcast(MessagePayload::TournamentFinished {
+            placements: top.clone(),
+        });
+        self.emit(SpectatorEvent::TournamentFinished {
+            tournament: self.tc.name.clone(),
+            placements: top
+                .into_iter()
+                .map(|p| Standing {
+                    rank: p.rank,
+                    name: p.name,
+                })
+                .collect(),
+        });
+        self.phase = Phase::Finished;
+        self.pending.clear();
+        self.underway.clear();
+        self.queue.clear();
+        Ok(())
+    }
+
+    /// Moves scheduled templates along: creates the next instance, opens its check-in
+    /// and starts it, each once its time has come.
+    fn run_schedule(&mut self) {
+        let now = Utc::now();
+        for i in 0..self.templates.all().len() {
+            let template = self.templates.all()[i].clone();
+            let Some(schedule) = template.schedule else {
+                continue;
+            };
+
+            let Some(run) = schedule.current.clone() else {
+                let start = schedule.next_start(now);
+                // only take over when nothing else is going on
+                let idle = self.phase == Phase::Finished
+                    || (self.phase == Phase::CollectingRoster && self.bracket.entrants.is_empty());
+                if now < schedule.create_at(start) || !idle {
+                    continue;
+                }
+                let options = schedule.instance(&template.options, start);
+                match crate::challonge::create_tournament(&self.c, &options) {
+                    Ok(tc) => {
+                        let run = ScheduledRun {
+                            url: tc.url.clone(),
+                            start_at: start,
+                        };
+                        self.switch_to(tc);
+                        let schedule = self.templates.all_mut()[i].schedule.as_mut().unwrap();
+                        schedule.next_number += 1;
+                        schedule.current = Some(run);
+                        self.templates.save();
+                    }
+                    Err(e) => self.record_error(format!(
+                        "could not create the scheduled {}: {}",
+                        template.name, e
+                    )),
+                }
+                return;
+            };
+
+            if self.tc.url != run.url {
+                continue;
+            }
+            if self.phase == Phase::CollectingRoster && now >= run.check_in_at(&schedule) {
+                if let Err(e) = self.open_check_in(run.start_at) {
+                    self.record_error(e);
+                }
+            }
+            if now >= run.start_at
+                && matches!(
+                    self.phase,
+                    Phase::CollectingRoster | Phase::CheckingIn | Phase::RosterLocked
+                )
+            {
+                // left as it is so the next tick tries again
+                if self.phase != Phase::RosterLocked {
+                    if let Err(e) = self.lock_roster() {
+                        self.record_error(e);
+                        return;
+                    }
+                }
+                if self.players.len() < 2 {
+                    // nobody to play, skip this one so the next week's still gets made
+                    self.record_error(format!(
+                        "skipping {}, only {} checked in",
+                        run.url,
+                        self.players.len()
+                    ));
+                    self.phase = Phase::Finished;
+                } else if let Err(e) = self.start_bracket() {
+                    self.record_error(e);
+                    return;
+                } else {
+                    self.broadcast(MessagePayload::TournamentStart {});
+                }
+                self.templates.all_mut()[i]
+                    .schedule
+                    .as_mut()
+                    .unwrap()
+                    .current = None;
+                self.templates.save();
+            }
+            return;
+        }
+    }
+
+    /// Leaves the current tournament for `tc`, which is remembered across restarts.
+    fn switch_to(&mut self, tc: challonge::Tournament) {
+        println!("switching to {} ({})", tc.name, tc.url);
+        if let Err(e) = std::fs::write(TOURNAMENT_FILE, &tc.url) {
+            self.record_error(format!("could not write {}: {}", TOURNAMENT_FILE, e));
+        }
+        self.tc = tc;
+        self.phase = Phase::CollectingRoster;
+        self.players.clear();
+        self.arena_to_match = vec![None; NUM_ARENAS];
+        self.pending.clear();
+        self.underway.clear();
+        self.queue.clear();
+        self.finished_at.clear();
+        self.round_arena_selection.clear();
+        self.arena_used.clear();
+        self.last_arena.clear();
+        self.held.clear();
+        self.cancelled.clear();
+        self.featured = None;
+        self.featured_pinned = false;
+        self.results.clear();
+        self.bracket = BracketCache::new();
+        self.refresh_entrants();
+    }
+
+    /// Picks up a bracket that was already started before we came up, without adding
+    /// or starting anything on challonge.
+    fn resume(&mut self) {
+        self.roster_from_entrants();
+        // final ranks only show up once it's been finalized, it's over and archived
+        if self
+            .bracket
+            .entrants
+            .values()
+            .any(|e| e.final_rank.is_some())
+        {
+            println!("{} is already finished", self.tc.name);
+            self.phase = Phase::Finished;
+            return;
+        }
+        println!("{} is already running, resuming", self.tc.name);
+        self.phase = Phase::Running;
+
+        let steam_id =
+            |pid: Option<u64>| pid.and_then(|pid| self.bracket.entrants.get(&pid)?.steam_id);
+        let mut completed: Vec<&Match> = self
+            .bracket
+            .matches
+            .iter()
+            .filter(|m| m.winner_id.is_some())
+            .collect();
+        completed.sort_by_key(|m| (m.suggested_play_order, m.id));
+        self.results = completed
+            .into_iter()
+            .filter_map(|m| {
+                let loser = if m.winner_id == m.player1_id {
+                    m.player2_id
+                } else {
+                    m.player1_id
+                };
+                Some(ReportedResult {
+                    match_id: m.id,
+                    arena: -1,
+                    winner: steam_id(m.winner_id)?,
+                    loser: steam_id(loser)?,
+                    disputed: None,
+                })
+            })
+            .collect();
+
+        let open: Vec<u64> = self
+            .bracket
+            .matches
+            .iter()
+            .filter(|m| m.state == "open")
+            .map(|m| m.id)
+            .collect();
+        let saved = SavedArenas::load(&self.tc.id.to_string()).unwrap_or_default();
+        if let Some(phase @ (Phase::Paused | Phase::Stopped)) = saved.phase {
+            println!("{} was {:?}, leaving it that way", self.tc.name, phase);
+            self.phase = phase;
+        }
+        self.held = saved.held;
+        for (arena, mtch) in saved.arenas.into_iter().enumerate().take(NUM_ARENAS) {
+            if mtch
+                .as_ref()
+                .is_some_and(|m| m.match_id.is_some_and(|id| open.contains(&id)))
+            {
+                self.arena_to_match[arena] = mtch;
+            }
         }
+        // held results wait for the admin to resume, like they would have without the restart
+        if self.phase == Phase::Running {
+            self.report_held();
+        }
+        self.underway =
+            crate::challonge::pending_from(&self.bracket.matches, &self.bracket.entrants)
+                .into_iter()
+                .filter(|(match_id, _, _)| {
+                    self.bracket
+                        .matches
+                        .iter()
+                        .any(|m| m.id == *match_id && m.underway_at.is_some())
+                        && !self
+                            .arena_to_match
+                            .iter()
+                            .flatten()
+                            .any(|a| a.match_id == Some(*match_id))
+                })
+                .map(|(match_id, (_, p1_id), (_, p2_id))| PendingMatch {
+                    match_id,
+                    p1_id,
+                    p2_id,
+                })
+                .collect();
     }
 
+    /// Hands out whatever is already open in the cached bracket straight away, then
+    /// catches up with challonge and hands out anything that opened since.
     pub fn send_pending_matches(&mut self) {
-        let pending = crate::challonge::pending_matches(&self.c, &self.tc);
-        'outer: for ((p1, p1id), (p2, p2id)) in pending {
+        self.dispatch_pending();
+        match self.bracket.refresh_matches(&self.tc.id) {
+            Ok(true) => self.dispatch_pending(),
+            Ok(false) => {}
+            Err(e) => self.record_error(e),
+        }
+    }
+
+    fn dispatch_pending(&mut self) {
+        self.drop_stale_assignments();
+        let pending = crate::challonge::pending_from(&self.bracket.matches, &self.bracket.entrants);
+        // still being played somewhere as long as it's open
+        self.underway.retain(|u| {
+            pending
+                .iter()
+                .any(|(match_id, _, _)| *match_id == u.match_id)
+        });
+        // a forfeit reported some other way settles it
+        self.cancelled.retain(|c| {
+            pending
+                .iter()
+                .any(|(match_id, _, _)| *match_id == c.match_id)
+        });
+        self.pending.clear();
+        // the policy only orders what just became ready, whatever was already waiting
+        // for an arena goes first and keeps its place
+        let mut pending = pending;
+        crate::dispatch::sort(
+            self.dispatch.as_ref(),
+            &self.bracket.matches,
+            &mut pending,
+            |(match_id, _, _)| *match_id,
+        );
+        pending.sort_by_key(|(match_id, _, _)| {
+            self.queue
+                .iter()
+                .position(|q| q.match_id == *match_id)
+                .unwrap_or(usize::MAX)
+        });
+        self.queue.clear();
+        // the featured match goes first so it gets the showcase arena
+        self.update_featured(&pending);
+        pending.sort_by_key(|(match_id, _, _)| Some(*match_id) != self.featured);
+        'outer: for (match_id, (_, p1id), (_, p2id)) in pending {
+            if self.cancelled.iter().any(|c| c.match_id == match_id) {
+                continue;
+            }
+            for u in &self.underway {
+                if u.match_id == match_id {
+                    continue 'outer;
+                }
+                if [u.p1_id, u.p2_id]
+                    .iter()
+                    .any(|id| *id == p1id || *id == p2id)
+                {
+                    self.pending.push(PendingMatch {
+                        match_id,
+                        p1_id: p1id,
+                        p2_id: p2id,
+                    });
+                    continue 'outer;
+                }
+            }
             // skip pending matches that are currently getting played
-            for arena in &self.arena_to_match {
-                if let Some(mtch) = arena {
-                    if mtch.contains(&p1id) || mtch.contains(&p2id) {
-                        continue 'outer;
-                    }
+            for mtch in self.arena_to_match.iter().flatten() {
+                if mtch.match_id == Some(match_id) {
+                    continue 'outer;
                 }
+                if mtch.contains(&p1id) || mtch.contains(&p2id) {
+                    self.pending.push(PendingMatch {
+                        match_id,
+                        p1_id: p1id,
+                        p2_id: p2id,
+                    });
+                    continue 'outer;
+                }
+            }
+            // finals get played straight away, everyone is waiting on them anyway
+            if !self.is_final(match_id) && (self.resting(&p1id) || self.resting(&p2id)) {
+                self.pending.push(PendingMatch {
+                    match_id,
+                    p1_id: p1id,
+                    p2_id: p2id,
+                });
+                continue;
             }
-            let arena = get_open_arena(&self.arena_to_match, &self.arena_priority_order).unwrap();
+            let showcase = self
+                .showcase_arena()
+                .filter(|&arena| self.arena_to_match[arena].is_none());
+            let arena = match showcase {
+                Some(showcase) if self.featured == Some(match_id) => Some(showcase),
+                _ => self.pick_arena(match_id, p1id, p2id),
+            };
+            let Some(arena) = arena else {
+                self.queue.push(PendingMatch {
+                    match_id,
+                    p1_id: p1id,
+                    p2_id: p2id,
+                });
+                continue;
+            };
 
-            let mut mtch = HashSet::new();
-            mtch.insert(p1id.clone());
-            mtch.insert(p2id.clone());
-            self.arena_to_match[arena] = Some(mtch);
+            self.arena_to_match[arena] = Some(ArenaMatch::new(Some(match_id), p1id, p2id));
+            self.arena_used.insert(arena, Utc::now());
+            self.set_underway(match_id, true);
+            self.emit(SpectatorEvent::MatchAssigned {
+                arena: arena as i32,
+                p1: self.event_player(&p1id),
+                p2: self.event_player(&p2id),
+            });
 
             for server in &self.servers {
-                server.do_send(ForwardMessage {
+                server.addr.do_send(ForwardMessage {
                     message: crate::MessagePayload::MatchDetails {
-                        arenaId: arena as i32,
-                        p1Id: p1id.clone(),
-                        p2Id: p2id.clone(),
+                        arena_id: arena as i32,
+                        p1_id: p1id,
+                        p2_id: p2id,
                     },
-                    from: server.clone(),
+                    from: server.addr.clone(),
                 });
             }
         }
         println!("arenas {:?}", self.arena_to_match);
     }
+
+    fn refresh_entrants(&mut self) {
+        if let Err(e) = self
+            .bracket
+            .refresh_entrants(&self.c, &self.tc, &mut self.participants)
+        {
+            self.record_error(e);
+        }
+    }
+
+    /// Catches up with edits made on the challonge site. Local state follows challonge
+    /// and every difference is shown to the admins.
+    fn reconcile(&mut self) {
+        if let Err(e) = self.bracket.refresh_matches(&self.tc.id) {
+            return self.record_error(e);
+        }
+        let results: Vec<(u64, SteamId)> = self
+            .results
+            .iter()
+            .map(|r| (r.match_id, r.winner))
+            .collect();
+        let arenas: Vec<(usize, u64, SteamId, SteamId)> = self
+            .arena_to_match
+            .iter()
+            .enumerate()
+            .filter_map(|(arena, m)| {
+                let m = m.as_ref()?;
+                Some((arena, m.match_id?, m.p1_id, m.p2_id))
+            })
+            .collect();
+        let found = crate::reconcile::reconcile(
+            &self.bracket.matches,
+            &self.bracket.entrants,
+            &results,
+            &arenas,
+        );
+
+        for discrepancy in found {
+            self.record_error(format!("challonge: {}", discrepancy));
+            match discrepancy {
+                Discrepancy::ResultRemoved { match_id } => {
+                    self.results.retain(|r| r.match_id != match_id);
+                    self.ratings.revert(match_id);
+                }
+                Discrepancy::ResultReopened { match_id } => {
+                    self.results.retain(|r| r.match_id != match_id);
+                    self.ratings.revert(match_id);
+                    self.emit(SpectatorEvent::MatchReopened { match_id });
+                }
+                Discrepancy::WinnerChanged { match_id, winner } => {
+                    let Some(result) = self.results.iter_mut().find(|r| r.match_id == match_id)
+                    else {
+                        continue;
+                    };
+                    if result.loser == winner {
+                        result.loser = result.winner;
+                    }
+                    result.winner = winner;
+                    let loser = result.loser;
+                    self.ratings.revert(match_id);
+                    self.rate(Some(match_id), &winner, &loser, None);
+                    self.emit(SpectatorEvent::ResultCorrected {
+                        match_id,
+                        winner: self.event_player(&winner),
+                        loser: self.event_player(&loser),
+                    });
+                }
+                Discrepancy::ArenaMatchRemoved { arena, .. }
+                | Discrepancy::ArenaMatchClosed { arena, .. }
+                | Discrepancy::ArenaPlayersChanged { arena, .. } => {
+                    self.cancel_arena(arena as i32);
+                }
+            }
+        }
+    }
+
+    /// Frees arenas whose bracket match was reset or changed underneath us, e.g. the
+    /// downstream matches of a reopened result.
+    fn drop_stale_assignments(&mut self) {
+        let mut stale = vec![];
+        for (arena, mtch) in self.arena_to_match.iter().enumerate() {
+            let Some(mtch) = mtch else { continue };
+            let Some(match_id) = mtch.match_id else {
+                continue;
+            };
+            let current = self.bracket.matches.iter().find(|m| m.id == match_id);
+            let still_valid = current.is_some_and(|m| {
+                let steam_id = |pid: Option<u64>| {
+                    pid.and_then(|pid| self.bracket.entrants.get(&pid))
+                        .and_then(|e| e.steam_id)
+                };
+                m.state == "open"
+                    && steam_id(m.player1_id).is_some_and(|id| mtch.contains(&id))
+                    && steam_id(m.player2_id).is_some_and(|id| mtch.contains(&id))
+            });
+            if !still_valid {
+                stale.push(arena);
+            }
+        }
+
+        for arena in stale {
+            println!("match in arena {} is no longer open, cancelling", arena);
+            self.cancel_arena(arena as i32);
+        }
+    }
+
+    fn cancel_arena(&mut self, arena: i32) {
+        if let Some(match_id) = self.arena_to_match[arena as usize]
+            .take()
+            .and_then(|m| m.match_id)
+        {
+            // it may have been reset on challonge already, in which case this is a no-op
+            self.set_underway(match_id, false);
+        }
+        self.emit(SpectatorEvent::MatchCancelled { arena });
+        for server in &self.servers {
+            server.addr.do_send(ForwardMessage {
+                message: MessagePayload::MatchCancel {
+                    delinquents: vec![],
+                    arrived: None,
+                    arena,
+                },
+                from: server.addr.clone(),
+            });
+        }
+    }
+
+    fn report_result(
+        &mut self,
+        arena: i32,
+        assigned: Option<u64>,
+        winner: SteamId,
+        loser: SteamId,
+        margin: Option<i32>,
+    ) {
+        let reported = crate::challonge::report_match(
+            &self.tc,
+            &self.bracket.matches,
+            &self.bracket.entrants,
+            winner,
+            loser,
+        );
+        match reported {
+            Ok(Some((match_id, winner_pid))) => {
+                self.bracket.reported(match_id, winner_pid);
+                self.rate(Some(match_id), &winner, &loser, margin);
+                self.results.push(ReportedResult {
+                    match_id,
+                    arena,
+                    winner,
+                    loser,
+                    disputed: None,
+                });
+            }
+            Ok(None) => {
+                // hand-assigned arenas have no bracket match, they still count for ratings
+                if assigned.is_none() {
+                    self.rate(None, &winner, &loser, margin);
+                }
+                self.record_error(format!(
+                    "no open match between {} and {} to report",
+                    winner, loser
+                ));
+            }
+            // nothing changes here unless challonge has the result too
+            Err(e) => self.record_error(format!(
+                "could not report {} beating {}: {}",
+                winner, loser, e
+            )),
+        }
+    }
+
+    /// Takes back the most recent result: held ones are just dropped, reported ones are
+    /// reopened on challonge and anything sent out because of them is called back.
+    fn undo_last_result(&mut self) -> Result<(), String> {
+        if let Some(held) = self.held.pop() {
+            println!(
+                "undid held result in arena {}: {} beat {}",
+                held.arena, held.winner, held.loser
+            );
+            self.finished_at.remove(&held.winner);
+            self.finished_at.remove(&held.loser);
+            return Ok(());
+        }
+        let Some(result) = self.results.last().cloned() else {
+            return Err("no result to undo".to_string());
+        };
+
+        // nothing is called back or announced unless challonge reopened it
+        let dependents = BracketGraph::new(&self.bracket.matches).dependents(result.match_id);
+        self.reopen(result.match_id)?;
+        println!(
+            "undoing match {}: {} beat {}",
+            result.match_id, result.winner, result.loser
+        );
+
+        // reopen already drops whatever challonge reset, this catches the rest
+        let dispatched: Vec<i32> = self
+            .arena_to_match
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| {
+                m.as_ref()
+                    .and_then(|m| m.match_id)
+                    .is_some_and(|id| dependents.contains(&id))
+            })
+            .map(|(arena, _)| arena as i32)
+            .collect();
+        for arena in dispatched {
+            self.cancel_arena(arena);
+        }
+
+        self.emit(SpectatorEvent::ResultUndone {
+            match_id: result.match_id,
+            winner: self.event_player(&result.winner),
+            loser: self.event_player(&result.loser),
+        });
+        self.finished_at.remove(&result.winner);
+        self.finished_at.remove(&result.loser);
+        if self.phase == Phase::Running {
+            self.send_pending_matches();
+        }
+        Ok(())
+    }
+
+    fn report_held(&mut self) {
+        for held in std::mem::take(&mut self.held) {
+            self.report_result(
+                held.arena,
+                held.match_id,
+                held.winner,
+                held.loser,
+                held.margin,
+            );
+        }
+    }
+
+    /// Reopens a reported match on challonge, which also resets every match that
+    /// depends on it, then brings our own state back in line.
+    fn reopen(&mut self, match_id: u64) -> Result<(), String> {
+        crate::challonge::reopen_match(&self.tc, match_id)?;
+        self.emit(SpectatorEvent::MatchReopened { match_id });
+        self.bracket.invalidate();
+        if let Err(e) = self.bracket.refresh_matches(&self.tc.id) {
+            self.record_error(e);
+        }
+        let matches = &self.bracket.matches;
+        let (kept, dropped) = self.results.drain(..).partition(|r| {
+            matches
+                .iter()
+                .any(|m| m.id == r.match_id && m.winner_id.is_some())
+        });
+        self.results = kept;
+        for result in dropped {
+            self.ratings.revert(result.match_id);
+        }
+        self.drop_stale_assignments();
+        Ok(())
+    }
+
+    fn correct_result(&mut self, match_id: u64, winner: SteamId) -> Result<(), String> {
+        let Some(m) = self
+            .bracket
+            .matches
+            .iter()
+            .find(|m| m.id == match_id)
+            .cloned()
+        else {
+            return Err(format!("no match with id {}", match_id));
+        };
+        let pid_of = |steam_id: &SteamId| {
+            self.bracket
+                .entrants
+                .iter()
+                .find(|(_, e)| e.steam_id == Some(*steam_id))
+                .map(|(pid, _)| *pid)
+        };
+        let Some(winner_pid) = pid_of(&winner) else {
+            return Err(format!("{} is not in the bracket", winner));
+        };
+        let (loser_pid, scoreline) = if m.player1_id == Some(winner_pid) {
+            (m.player2_id, "1-0")
+        } else if m.player2_id == Some(winner_pid) {
+            (m.player1_id, "0-1")
+        } else {
+            return Err(format!("{} did not play in match {}", winner, match_id));
+        };
+        let Some(loser) = loser_pid
+            .and_then(|pid| self.bracket.entrants.get(&pid))
+            .and_then(|e| e.steam_id)
+        else {
+            return Err(format!("match {} has no opponent", match_id));
+        };
+
+        let arena = self
+            .results
+            .iter()
+            .find(|r| r.match_id == match_id)
+            .map_or(-1, |r| r.arena);
+
+        if m.winner_id.is_some() {
+            self.reopen(match_id)?;
+        }
+        crate::challonge::update_match(&self.tc, &m, &winner_pid, scoreline)?;
+        self.bracket.reported(match_id, winner_pid);
+
+        self.results.retain(|r| r.match_id != match_id);
+        self.ratings.revert(match_id);
+        self.rate(Some(match_id), &winner, &loser, None);
+        self.results.push(ReportedResult {
+            match_id,
+            arena,
+            winner,
+            loser,
+            disputed: None,
+        });
+        self.emit(SpectatorEvent::ResultCorrected {
+            match_id,
+            winner: self.event_player(&winner),
+            loser: self.event_player(&loser),
+        });
+        Ok(())
+    }
+
+    /// Adds the player to the bracket unless they are already in it.
+    fn register(&mut self, steam_id: &SteamId, name: &String) -> Result<u64, String> {
+        self.participants.seen_as(steam_id, name);
+        if let Some((pid, _)) = self
+            .bracket
+            .entrants
+            .iter()
+            .find(|(_, e)| e.steam_id == Some(*steam_id))
+        {
+            return Ok(*pid);
+        }
+        println!("adding player {:?}", name);
+        let pid = crate::challonge::add_participant(&self.tc, name, steam_id)?;
+        self.participants
+            .insert(pid, &self.tc.id.to_string(), *steam_id, name);
+        self.bracket.entrants.insert(
+            pid,
+            Entrant {
+                name: name.clone(),
+                steam_id: Some(*steam_id),
+                final_rank: None,
+                checked_in: false,
+                active: true,
+            },
+        );
+        Ok(pid)
+    }
+
+    /// Late registrations are fine as long as the roster isn't locked yet.
+    fn check_in(&mut self, steam_id: SteamId, name: String) -> Result<(), String> {
+        if self.phase != Phase::CheckingIn {
+            return Err(format!("check-in is not open ({:?})", self.phase));
+        }
+        let pid = self
+            .register(&steam_id, &name)
+            .map_err(|e| format!("could not register {}: {}", name, e))?;
+
+        crate::challonge::check_in_participant(&self.tc, pid)?;
+        if let Some(entrant) = self.bracket.entrants.get_mut(&pid) {
+            entrant.checked_in = true;
+        }
+        Ok(())
+    }
+
+    fn rate(
+        &mut self,
+        match_id: Option<u64>,
+        winner: &SteamId,
+        loser: &SteamId,
+        margin: Option<i32>,
+    ) {
+        let (winner_name, loser_name) = (self.display_name(winner), self.display_name(loser));
+        self.ratings.record_result(
+            match_id,
+            (winner, &winner_name),
+            (loser, &loser_name),
+            margin,
+        );
+    }
+
+    /// Entrants that will make it into the bracket, best rated first.
+    fn seeded_entrants(&self) -> Vec<(u64, &Entrant)> {
+        let mut seeded: Vec<(u64, &Entrant)> = self
+            .bracket
+            .entrants
+            .iter()
+            .filter(|(_, e)| e.active && (self.phase != Phase::CheckingIn || e.checked_in))
+            .map(|(pid, e)| (*pid, e))
+            .collect();
+        seeded.sort_by(|(_, a), (_, b)| {
+            self.seed_rating(b)
+                .total_cmp(&self.seed_rating(a))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        seeded
+    }
+
+    fn seed_rating(&self, entrant: &Entrant) -> f64 {
+        entrant
+            .steam_id
+            .map_or(DEFAULT_RATING, |id| self.ratings.rating(&id))
+    }
+
+    /// Pushes the rating order to challonge as seeds.
+    fn apply_seeding(&mut self) {
+        let seeded: Vec<u64> = self.seeded_entrants().iter().map(|(pid, _)| *pid).collect();
+        for (i, pid) in seeded.into_iter().enumerate() {
+            // every seed after a failed one would land in the wrong place anyway
+            if let Err(e) = crate::challonge::set_seed(&self.tc, pid, i + 1) {
+                self.record_error(format!("could not seed {}: {}", pid, e));
+                return;
+            }
+        }
+    }
+
+    fn set_underway(&mut self, match_id: u64, underway: bool) {
+        if let Err(e) = crate::challonge::mark_underway(&self.tc, match_id, underway) {
+            self.record_error(format!("match {}: {}", match_id, e));
+        }
+    }
+
+    fn is_admin(&self, addr: &actix::Addr<ServerWs>) -> bool {
+        self.admins.contains(addr)
+    }
+
+    fn server_num(&self, addr: &actix::Addr<ServerWs>) -> Option<String> {
+        self.servers
+            .iter()
+            .find(|s| s.addr == *addr)
+            .map(|s| s.info.server_num.clone())
+    }
+
+    /// Remembers which game server is playing `arena` if nobody has claimed it yet.
+    fn claim_arena(&mut self, arena: usize, from: &actix::Addr<ServerWs>) {
+        let server = self.server_num(from);
+        if let Some(Some(m)) = self.arena_to_match.get_mut(arena) {
+            if m.server.is_none() {
+                m.server = server;
+            }
+        }
+    }
+
+    /// Puts a match we only know is underway into `arena` once a server reports it
+    /// from there, which is how those get their arena back after a restart.
+    fn claim_underway(
+        &mut self,
+        arena: usize,
+        winner: &SteamId,
+        loser: &SteamId,
+        from: &actix::Addr<ServerWs>,
+    ) {
+        let sender = self.sender(from);
+        let Some(i) = underway_claim(
+            self.arena_to_match[arena].as_ref(),
+            &self.underway,
+            &sender,
+            winner,
+            loser,
+        ) else {
+            return;
+        };
+        let u = self.underway.remove(i);
+        let mut m = ArenaMatch::new(Some(u.match_id), u.p1_id, u.p2_id);
+        m.server = self.server_num(from);
+        // keeps the match length estimate honest
+        if let Some(started) = self
+            .bracket
+            .matches
+            .iter()
+            .find(|bm| bm.id == u.match_id)
+            .and_then(|bm| bm.underway_at.as_deref()?.parse().ok())
+        {
+            m.assigned_at = started;
+        }
+        self.arena_to_match[arena] = Some(m);
+    }
+
+    fn sender(&self, from: &actix::Addr<ServerWs>) -> Sender {
+        if self.is_admin(from) {
+            Sender::Admin
+        } else {
+            self.server_num(from)
+                .map_or(Sender::Unknown, Sender::Server)
+        }
+    }
+
+    fn reject(&mut self, from: &actix::Addr<ServerWs>, message: String) {
+        from.do_send(ForwardMessage {
+            message: MessagePayload::Error {
+                message: message.clone(),
+            },
+            from: from.clone(),
+        });
+        self.record_error(message);
+    }
+
+    /// Rejects admin-only messages from anyone else, telling the sender why.
+    fn require_admin(&mut self, from: &actix::Addr<ServerWs>, what: &str) -> bool {
+        if self.is_admin(from) {
+            return true;
+        }
+        self.reject(from, format!("{} is only accepted from admins", what));
+        false
+    }
+
+    fn require_phase(
+        &mut self,
+        from: &actix::Addr<ServerWs>,
+        what: &str,
+        allowed: &[Phase],
+    ) -> bool {
+        if allowed.contains(&self.phase) {
+            return true;
+        }
+        self.reject(
+            from,
+            format!(
+                "{} is not accepted while the tournament is {:?}",
+                what, self.phase
+            ),
+        );
+        false
+    }
+
+    fn roster_from_entrants(&mut self) {
+        self.players = self
+            .bracket
+            .entrants
+            .values()
+            .filter(|e| e.active)
+            .filter_map(|e| {
+                Some(crate::Player {
+                    steam_id: e.steam_id?,
+                    name: e.name.clone(),
+                })
+            })
+            .collect();
+    }
+
+    fn record_error(&mut self, message: String) {
+        println!("recieved error {:?}", message);
+        if self.errors.len() == MAX_RECENT_ERRORS {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(format!(
+            "{} {}",
+            chrono::Local::now().format("%H:%M:%S"),
+            message
+        ));
+    }
+
+    fn valid_arena(&mut self, arena: i32) -> bool {
+        if arena < 0 || arena as usize >= NUM_ARENAS {
+            self.record_error(format!("arena {} does not exist", arena));
+            return false;
+        }
+        true
+    }
+
+    /// Arenas in priority order, each once and only the ones that exist.
+    fn arena_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = vec![];
+        for &arena in &self.arena_priority_order {
+            let arena = arena as usize;
+            if arena < NUM_ARENAS && !order.contains(&arena) {
+                order.push(arena);
+            }
+        }
+        order
+    }
+
+    fn pick_arena(&self, match_id: u64, p1: SteamId, p2: SteamId) -> Option<usize> {
+        let order = self.arena_order();
+        // the showcase arena is held for the featured match until it gets played
+        let reserved = self.featured.filter(|&id| {
+            id != match_id
+                && !self
+                    .arena_to_match
+                    .iter()
+                    .flatten()
+                    .any(|a| a.match_id == Some(id))
+        });
+        let showcase = reserved.and(self.showcase_arena());
+        let free: Vec<usize> = order
+            .iter()
+            .copied()
+            .filter(|&arena| self.arena_to_match[arena].is_none() && Some(arena) != showcase)
+            .collect();
+        let round = self
+            .bracket
+            .matches
+            .iter()
+            .find(|m| m.id == match_id)
+            .map(|m| m.round);
+        let rounds = self.bracket.matches.iter().map(|m| m.round);
+        let late = match round {
+            Some(round) if round > 0 => rounds.max().is_some_and(|last| round >= last - 1),
+            Some(round) => rounds.min().is_some_and(|last| round <= last + 1),
+            None => false,
+        };
+        let selection = round
+            .and_then(|round| self.round_arena_selection.get(&round))
+            .unwrap_or(&self.arena_selection);
+        selection.pick(&ArenaRequest {
+            free: &free,
+            showcase: self.showcase_arena(),
+            p1,
+            p2,
+            late,
+            last_used: &self.arena_used,
+            last_arena: &self.last_arena,
+        })
+    }
+
+    /// Where the featured match gets played, the first arena in the priority order.
+    fn showcase_arena(&self) -> Option<usize> {
+        self.arena_order().first().copied()
+    }
+
+    /// Keeps the featured match while it's pinned or being played, otherwise picks the
+    /// latest round, then the highest rated pair, of everything open.
+    fn update_featured(&mut self, ready: &[(u64, NamedPlayer, NamedPlayer)]) {
+        if let Some(id) = self.featured {
+            let open = self
+                .bracket
+                .matches
+                .iter()
+                .any(|m| m.id == id && m.state == "open");
+            let playing = self
+                .arena_to_match
+                .iter()
+                .flatten()
+                .any(|a| a.match_id == Some(id));
+            if open && (self.featured_pinned || playing) {
+                return;
+            }
+        }
+        self.featured_pinned = false;
+
+        let mut candidates: Vec<(u64, SteamId, SteamId)> = self
+            .arena_to_match
+            .iter()
+            .flatten()
+            .filter_map(|a| Some((a.match_id?, a.p1_id, a.p2_id)))
+            .collect();
+        candidates.extend(ready.iter().map(|(id, (_, p1), (_, p2))| (*id, *p1, *p2)));
+        let graph = BracketGraph::new(&self.bracket.matches);
+        self.featured = candidates
+            .into_iter()
+            .map(|(id, p1, p2)| {
+                let rating = self.ratings.rating(&p1) + self.ratings.rating(&p2);
+                (id, graph.height(id), rating)
+            })
+            .min_by(|a, b| a.1.cmp(&b.1).then(b.2.total_cmp(&a.2)))
+            .map(|(id, _, _)| id);
+    }
+
+    fn featured_view(&self) -> Option<FeaturedMatch> {
+        let id = self.featured?;
+        let m = self.bracket.matches.iter().find(|m| m.id == id)?;
+        let arena = self
+            .arena_to_match
+            .iter()
+            .position(|a| a.as_ref().is_some_and(|a| a.match_id == Some(id)));
+        let player = |pid: Option<u64>| {
+            pid.and_then(|pid| self.bracket.entrants.get(&pid))
+                .map(|e| e.name.clone())
+                .unwrap_or_default()
+        };
+        let (p1, p2, p1_score, p2_score) = match arena.and_then(|a| self.arena_to_match[a].as_ref())
+        {
+            Some(a) => (
+                self.display_name(&a.p1_id),
+                self.display_name(&a.p2_id),
+                a.p1_score,
+                a.p2_score,
+            ),
+            None => (player(m.player1_id), player(m.player2_id), 0, 0),
+        };
+        // the server playing the arena, any server with STV until we know which that is
+        let owner = arena
+            .and_then(|a| self.arena_to_match[a].as_ref())
+            .and_then(|a| a.server.as_ref());
+        let stv = match owner {
+            Some(owner) => self.servers.iter().find(|s| s.info.server_num == *owner),
+            None => self.servers.iter().find(|s| !s.info.stv_port.is_empty()),
+        }
+        .filter(|s| !s.info.stv_port.is_empty())
+        .map(|s| format!("connect {}:{}", s.info.server_host, s.info.stv_port));
+        Some(FeaturedMatch {
+            match_id: id,
+            round: m.round,
+            arena: arena.map(|a| a as i32),
+            p1,
+            p2,
+            p1_score,
+            p2_score,
+            pinned: self.featured_pinned,
+            stv,
+        })
+    }
+
+    fn rest_left(&self, steam_id: &SteamId) -> TimeDelta {
+        rest_left(
+            self.finished_at.get(steam_id).copied(),
+            self.min_rest,
+            Utc::now(),
+        )
+    }
+
+    fn resting(&self, steam_id: &SteamId) -> bool {
+        self.rest_left(steam_id) > TimeDelta::zero()
+    }
+
+    /// The last round of the bracket, grand finals included.
+    fn is_final(&self, match_id: u64) -> bool {
+        let last = self.bracket.matches.iter().map(|m| m.round).max();
+        self.bracket
+            .matches
+            .iter()
+            .any(|m| m.id == match_id && Some(m.round) == last)
+    }
+
+    fn played_for(&mut self, duration: TimeDelta) {
+        if self.match_minutes.len() == RECENT_MATCHES {
+            self.match_minutes.pop_front();
+        }
+        self.match_minutes
+            .push_back(duration.num_seconds() as f64 / 60.0);
+    }
+
+    /// The arena queue with a rough wait for each match: queued matches take the
+    /// arenas in the order they free up, each match lasting as long as recent ones did.
+    fn queue_view(&self) -> Vec<QueuedMatch> {
+        let average = if self.match_minutes.is_empty() {
+            DEFAULT_MATCH_MINUTES
+        } else {
+            self.match_minutes.iter().sum::<f64>() / self.match_minutes.len() as f64
+        };
+        let now = Utc::now();
+        // minutes until each arena is free again
+        let free_in: Vec<f64> = self
+            .arena_order()
+            .into_iter()
+            .map(|arena| match &self.arena_to_match[arena] {
+                Some(m) => (average - (now - m.assigned_at).num_seconds() as f64 / 60.0).max(0.0),
+                None => 0.0,
+            })
+            .collect();
+
+        self.queue
+            .iter()
+            .zip(queue_waits(free_in, average, self.queue.len()))
+            .enumerate()
+            .map(|(i, (m, wait))| QueuedMatch {
+                match_id: m.match_id,
+                position: i + 1,
+                p1: self.display_name(&m.p1_id),
+                p2: self.display_name(&m.p2_id),
+                wait_minutes: wait.round() as u32,
+            })
+            .collect()
+    }
+
+    fn dashboard(&self) -> Dashboard {
+        let mut arenas: Vec<i32> = self.arena_order().into_iter().map(|a| a as i32).collect();
+        // manually assigned arenas that aren't in the priority order
+        for (arena, mtch) in self.arena_to_match.iter().enumerate() {
+            if mtch.is_some() && !arenas.contains(&(arena as i32)) {
+                arenas.push(arena as i32);
+            }
+        }
+
+        let seeding: Vec<Seed> = self
+            .seeded_entrants()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, e))| Seed {
+                seed: i + 1,
+                steam_id: e.steam_id,
+                name: e.name.clone(),
+                rating: self.seed_rating(e),
+                overridden: e
+                    .steam_id
+                    .and_then(|id| self.ratings.get(&id))
+                    .is_some_and(|r| r.override_rating.is_some()),
+            })
+            .collect();
+
+        Dashboard {
+            tournament: self.tc.name.clone(),
+            arenas: arenas
+                .into_iter()
+                .map(|arena| ArenaView {
+                    arena,
+                    mtch: self.arena_to_match[arena as usize].clone(),
+                })
+                .collect(),
+            pending: self.pending.clone(),
+            underway: self.underway.clone(),
+            queue: self.queue_view(),
+            held: self.held.clone(),
+            cancelled: self.cancelled.clone(),
+            featured: self.featured_view(),
+            resting: self
+                .finished_at
+                .keys()
+                .filter(|id| self.resting(id))
+                .map(|&steam_id| Resting {
+                    steam_id,
+                    seconds_left: self.rest_left(&steam_id).num_seconds(),
+                })
+                .collect(),
+            min_rest_secs: self.min_rest.num_seconds(),
+            arena_selection: self.arena_selection.name().to_string(),
+            round_arena_selection: self
+                .round_arena_selection
+                .iter()
+                .map(|(&round, s)| RoundSelection {
+                    round,
+                    selection: s.name().to_string(),
+                })
+                .collect(),
+            arena_selections: crate::arenas::selections()
+                .iter()
+                .map(|s| s.name().to_string())
+                .collect(),
+            results: self.results.clone(),
+            servers: self.servers.iter().map(|s| s.info.clone()).collect(),
+            roster: self.players.clone(),
+            phase: self.phase,
+            registered: self
+                .bracket
+                .entrants
+                .values()
+                .filter(|e| e.active)
+                .map(|e| Registration {
+                    steam_id: e.steam_id,
+                    name: e.name.clone(),
+                    checked_in: e.checked_in,
+                })
+                .collect(),
+            seeding: seeding.clone(),
+            pairings: crate::seeding::first_round(&seeding)
+                .into_iter()
+                .map(|(p1, p2)| Pairing {
+                    p1: p1.name,
+                    p2: p2.map(|p| p.name),
+                })
+                .collect(),
+            templates: self.templates.all().to_vec(),
+            dispatch_policy: self.dispatch.name().to_string(),
+            dispatch_policies: crate::dispatch::policies()
+                .iter()
+                .map(|p| p.name().to_string())
+                .collect(),
+            errors: self.errors.iter().cloned().collect(),
+        }
+    }
+
+    fn push_dashboard(&self) {
+        if self.admins.is_empty() {
+            return;
+        }
+        let state = Box::new(self.dashboard());
+        for admin in &self.admins {
+            admin.do_send(ForwardMessage {
+                message: MessagePayload::Dashboard {
+                    state: state.clone(),
+                },
+                from: admin.clone(),
+            });
+        }
+    }
+
+    fn display_name(&self, steam_id: &SteamId) -> String {
+        if let Some(p) = self.players.iter().find(|p| p.steam_id == *steam_id) {
+            return p.name.clone();
+        }
+        if let Some(e) = self
+            .bracket
+            .entrants
+            .values()
+            .find(|e| e.steam_id == Some(*steam_id))
+        {
+            return e.name.clone();
+        }
+        "unknown".to_string()
+    }
+
+    fn event_player(&self, steam_id: &SteamId) -> EventPlayer {
+        EventPlayer {
+            name: self.display_name(steam_id),
+        }
+    }
+
+    fn emit(&mut self, event: SpectatorEvent) {
+        let update = self.events.push(event).to_update();
+        for subscriber in &self.event_subscribers {
+            subscriber.do_send(update.clone());
+        }
+    }
+
+    fn public_state(&self) -> PublicState {
+        let entrant_name = |id: Option<u64>| -> Option<String> {
+            id.and_then(|id| self.bracket.entrants.get(&id))
+                .map(|e| e.name.clone())
+        };
+
+        let mut standings: Vec<Standing> = self
+            .bracket
+            .entrants
+            .values()
+            .filter_map(|e| {
+                e.final_rank.map(|rank| Standing {
+                    rank,
+                    name: e.name.clone(),
+                })
+            })
+            .collect();
+        standings.sort_by_key(|s| s.rank);
+
+        let mut matches: Vec<&Match> = self.bracket.matches.iter().collect();
+        matches.sort_by_key(|m| (m.suggested_play_order, m.id));
+
+        PublicState {
+            tournament: self.tc.name.clone(),
+            challonge_url: self.tc.full_challonge_url.clone(),
+            bracket: matches
+                .into_iter()
+                .map(|m| PublicMatch {
+                    id: m.id,
+                    round: m.round,
+                    state: m.state.clone(),
+                    p1: entrant_name(m.player1_id),
+                    p2: entrant_name(m.player2_id),
+                    winner: entrant_name(m.winner_id),
+                    scores: m.scores_csv.clone(),
+                })
+                .collect(),
+            arenas: self
+                .arena_to_match
+                .iter()
+                .enumerate()
+                .filter_map(|(arena, mtch)| {
+                    mtch.as_ref().map(|mtch| LiveArena {
+                        arena: arena as i32,
+                        p1: self.display_name(&mtch.p1_id),
+                        p2: self.display_name(&mtch.p2_id),
+                        p1_score: mtch.p1_score,
+                        p2_score: mtch.p2_score,
+                    })
+                })
+                .collect(),
+            upcoming: self
+                .pending
+                .iter()
+                .map(|m| UpcomingMatch {
+                    p1: self.display_name(&m.p1_id),
+                    p2: self.display_name(&m.p2_id),
+                })
+                .collect(),
+            queue: self.queue_view(),
+            featured: self.featured_view(),
+            standings,
+            phase: self.phase,
+        }
+    }
+
+    fn push_public(&self) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let update = PublicUpdate(serde_json::to_string(&self.public_state()).unwrap());
+        for subscriber in &self.subscribers {
+            subscriber.do_send(update.clone());
+        }
+    }
+
+    fn publish(&self) {
+        self.push_dashboard();
+        self.push_public();
+        if matches!(self.phase, Phase::Running | Phase::Paused | Phase::Stopped) {
+            SavedArenas::save(
+                &self.tc.id.to_string(),
+                self.phase,
+                &self.arena_to_match,
+                &self.held,
+            );
+        }
+    }
+
+    fn broadcast(&self, message: MessagePayload) {
+        for server in &self.servers {
+            server.addr.do_send(ForwardMessage {
+                message: message.clone(),
+                from: server.addr.clone(),
+            });
+        }
+    }
 }
 
 impl Actor for Tournament {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(SCHEDULE_INTERVAL, |act, _ctx| {
+            act.run_schedule();
+            act.publish();
+        });
+        ctx.run_interval(REST_CHECK_INTERVAL, |act, _ctx| {
+            // a pending match whose players are free was only held back for their rest
+            let rested = act.pending.iter().any(|m| {
+                [m.p1_id, m.p2_id].iter().all(|id| {
+                    !act.resting(id)
+                        && !act.arena_to_match.iter().flatten().any(|a| a.contains(id))
+                        && !act
+                            .underway
+                            .iter()
+                            .any(|u| u.p1_id == *id || u.p2_id == *id)
+                })
+            });
+            if act.phase == Phase::Running && rested {
+                act.dispatch_pending();
+                act.publish();
+            }
+        });
+        // picks up anything changed on the challonge site
+        ctx.run_interval(REFRESH_INTERVAL, |act, _ctx| {
+            if act.phase == Phase::Running {
+                act.reconcile();
+                act.send_pending_matches();
+                act.finish_if_complete();
+                act.publish();
+            }
+        });
+    }
 }
 
 use crate::MessagePayload;
-use reqwest;
 
 impl StreamHandler<Result<Response<()>, reqwest::Error>> for Tournament {
     fn handle(&mut self, msg: Result<Response<()>, reqwest::Error>, _ctx: &mut Self::Context) {
@@ -108,109 +1900,708 @@ impl StreamHandler<Result<Response<()>, reqwest::Error>> for Tournament {
     }
 }
 
+impl Handler<Subscribe> for Tournament {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _ctx: &mut Self::Context) {
+        msg.addr.do_send(PublicUpdate(
+            serde_json::to_string(&self.public_state()).unwrap(),
+        ));
+        self.subscribers.push(msg.addr);
+    }
+}
+
+impl Handler<Unsubscribe> for Tournament {
+    type Result = ();
+
+    fn handle(&mut self, msg: Unsubscribe, _ctx: &mut Self::Context) {
+        self.subscribers.retain(|s| s != &msg.addr);
+    }
+}
+
+impl Handler<SubscribeEvents> for Tournament {
+    type Result = ();
+
+    fn handle(&mut self, msg: SubscribeEvents, _ctx: &mut Self::Context) {
+        if let Some(since) = msg.since {
+            for event in self.events.since(since) {
+                msg.addr.do_send(event.to_update());
+            }
+        }
+        self.event_subscribers.push(msg.addr);
+    }
+}
+
+impl Handler<UnsubscribeEvents> for Tournament {
+    type Result = ();
+
+    fn handle(&mut self, msg: UnsubscribeEvents, _ctx: &mut Self::Context) {
+        self.event_subscribers.retain(|s| s != &msg.addr);
+    }
+}
+
+impl Handler<GetPublicState> for Tournament {
+    type Result = PublicState;
+
+    fn handle(&mut self, _msg: GetPublicState, _ctx: &mut Self::Context) -> PublicState {
+        self.public_state()
+    }
+}
+
+impl Handler<GetFeatured> for Tournament {
+    type Result = Option<FeaturedMatch>;
+
+    fn handle(&mut self, _msg: GetFeatured, _ctx: &mut Self::Context) -> Self::Result {
+        self.featured_view()
+    }
+}
+
+impl Handler<GetLeaderboard> for Tournament {
+    type Result = Vec<LeaderboardEntry>;
+
+    fn handle(&mut self, _msg: GetLeaderboard, _ctx: &mut Self::Context) -> Self::Result {
+        self.ratings.leaderboard()
+    }
+}
+
+impl Handler<Disconnect> for Tournament {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, _ctx: &mut Self::Context) {
+        self.admins.retain(|a| a != &msg.addr);
+        self.servers.retain(|s| s.addr != msg.addr);
+        self.push_dashboard();
+    }
+}
+
 impl Handler<ForwardMessage> for Tournament {
     type Result = ();
 
     fn handle(&mut self, msg: ForwardMessage, _ctx: &mut Self::Context) {
         match msg.message {
-            MessagePayload::ServerHello { apiKey, .. } => {
-                if apiKey == "admin" {
-                    self.admin = Some(msg.from);
+            MessagePayload::ServerHello {
+                api_key,
+                server_num,
+                server_host,
+                server_port,
+                stv_port,
+            } => {
+                // re-read every time like api_key.txt so it can be changed without a restart
+                let admin_key = std::fs::read_to_string(ADMIN_KEY_FILE)
+                    .map(|key| key.trim().to_string())
+                    .unwrap_or_default();
+                if !admin_key.is_empty() && api_key == admin_key {
+                    self.admins.push(msg.from);
+                } else if server_num.is_empty() {
+                    // the dashboard doesn't have a server number, game servers always do
+                    self.reject(&msg.from, "wrong admin key".to_string());
                 } else {
-                    self.servers.push(msg.from);
+                    self.servers.push(GameServer {
+                        addr: msg.from,
+                        info: ServerInfo {
+                            server_num,
+                            server_host,
+                            server_port,
+                            stv_port,
+                        },
+                    });
                 }
             }
             MessagePayload::MatchDetails {
-                arenaId,
-                p1Id,
-                p2Id,
+                arena_id,
+                p1_id,
+                p2_id,
             } => {
                 // this is for when we are receiving a match from the web ui, not likely scenario
-                if self.arena_to_match[arenaId as usize].is_some() {
-                    println!("warning! overriding match in arena {:?}", arenaId);
-                }
-                let mut mtch = HashSet::new();
-                mtch.insert(p1Id.clone());
-                mtch.insert(p2Id.clone());
-                self.arena_to_match[arenaId as usize] = Some(mtch);
-
-                for servers in &self.servers {
-                    servers.do_send(ForwardMessage {
-                        message: MessagePayload::MatchDetails {
-                            arenaId: arenaId.clone(),
-                            p1Id: p1Id.clone(),
-                            p2Id: p2Id.clone(),
-                        },
-                        from: msg.from.clone(),
-                    })
+                if !self.require_admin(&msg.from, "MatchDetails") || !self.valid_arena(arena_id) {
+                    return self.push_dashboard();
                 }
+                if self.arena_to_match[arena_id as usize].is_some() {
+                    println!("warning! overriding match in arena {:?}", arena_id);
+                }
+                self.arena_to_match[arena_id as usize] = Some(ArenaMatch::new(None, p1_id, p2_id));
+                self.emit(SpectatorEvent::MatchAssigned {
+                    arena: arena_id,
+                    p1: self.event_player(&p1_id),
+                    p2: self.event_player(&p2_id),
+                });
+
+                self.broadcast(MessagePayload::MatchDetails {
+                    arena_id,
+                    p1_id,
+                    p2_id,
+                });
             }
             MessagePayload::SetMatchScore {
-                arenaId,
-                p1Score,
-                p2Score,
+                arena_id,
+                p1_score,
+                p2_score,
             } => {
-                for server in &self.servers {
-                    server.do_send(ForwardMessage {
-                        message: MessagePayload::SetMatchScore {
-                            arenaId: arenaId.clone(),
-                            p1Score: p1Score.clone(),
-                            p2Score: p2Score.clone(),
-                        },
-                        from: msg.from.clone(),
-                    });
+                if !self.valid_arena(arena_id) {
+                    return self.push_dashboard();
                 }
+                self.claim_arena(arena_id as usize, &msg.from);
+                if let Some(mtch) = &mut self.arena_to_match[arena_id as usize] {
+                    mtch.p1_score = p1_score;
+                    mtch.p2_score = p2_score;
+                }
+                self.emit(SpectatorEvent::ScoreUpdate {
+                    arena: arena_id,
+                    p1_score,
+                    p2_score,
+                });
+                self.broadcast(MessagePayload::SetMatchScore {
+                    arena_id,
+                    p1_score,
+                    p2_score,
+                });
             }
             MessagePayload::TournamentStart {} => {
-                for server in &self.servers {
-                    server.do_send(ForwardMessage {
-                        message: MessagePayload::TournamentStart {},
-                        from: msg.from.clone(),
-                    });
+                if !self.require_admin(&msg.from, "TournamentStart") {
+                    return self.push_dashboard();
+                }
+                self.broadcast(MessagePayload::TournamentStart {});
+                // coming back from a TournamentStop, put the open matches back out
+                if self.phase == Phase::Stopped {
+                    self.phase = Phase::Running;
+                    self.report_held();
+                    self.send_pending_matches();
+                    self.finish_if_complete();
                 }
             }
             MessagePayload::TournamentStop {} => {
-                self.arena_to_match = vec![None; NUM_ARENAS];
-                for server in &self.servers {
-                    server.do_send(ForwardMessage {
-                        message: MessagePayload::TournamentStop {},
-                        from: msg.from.clone(),
-                    });
+                if !self.require_admin(&msg.from, "TournamentStop") {
+                    return self.push_dashboard();
+                }
+                let arenas = std::mem::replace(&mut self.arena_to_match, vec![None; NUM_ARENAS]);
+                for match_id in arenas.into_iter().flatten().filter_map(|m| m.match_id) {
+                    self.set_underway(match_id, false);
                 }
+                // nothing goes out again until a TournamentStart
+                if matches!(self.phase, Phase::Running | Phase::Paused) {
+                    self.phase = Phase::Stopped;
+                }
+                self.emit(SpectatorEvent::TournamentStopped {
+                    tournament: self.tc.name.clone(),
+                });
+                self.broadcast(MessagePayload::TournamentStop {});
+            }
+            MessagePayload::TournamentFinished { .. } => {
+                // only ever sent to the servers, admins use FinishTournament
+                self.reject(
+                    &msg.from,
+                    "TournamentFinished is sent by the backend".to_string(),
+                );
             }
             MessagePayload::MatchCancel {
                 delinquents,
                 arrived,
                 arena,
             } => {
-                // TODO TODO TODO TODO TOOD TODO TODO TODO TODO TOODO TO DO
-                self.arena_to_match[arena as usize] = None;
+                if !self.valid_arena(arena) {
+                    return self.push_dashboard();
+                }
+                let cancelled = self.arena_to_match[arena as usize].take();
+                if let Some(ArenaMatch {
+                    match_id: Some(match_id),
+                    p1_id,
+                    p2_id,
+                    ..
+                }) = cancelled
+                {
+                    self.set_underway(match_id, false);
+                    // still open on challonge, so it would go straight back out otherwise
+                    if !self.is_admin(&msg.from) {
+                        let names: Vec<String> =
+                            delinquents.iter().map(|id| self.display_name(id)).collect();
+                        self.record_error(format!(
+                            "arena {} was called off (no-show: {}), retry or forfeit it",
+                            arena,
+                            names.join(", ")
+                        ));
+                        self.cancelled.push(CancelledMatch {
+                            match_id,
+                            p1_id,
+                            p2_id,
+                            delinquents: delinquents.clone(),
+                        });
+                    }
+                }
+                self.emit(SpectatorEvent::MatchCancelled { arena });
+                // cancels from the dashboard have to reach the game servers
+                if self.is_admin(&msg.from) {
+                    self.broadcast(MessagePayload::MatchCancel {
+                        delinquents,
+                        arrived,
+                        arena,
+                    });
+                }
             }
             MessagePayload::MatchResults {
                 winner,
                 loser,
-                finished,
+                finished: _,
                 arena,
             } => {
-                crate::challonge::report_match(&self.c, &self.tc, winner, loser);
-                self.arena_to_match[arena as usize] = None;
-                self.send_pending_matches();
+                if !self.require_phase(&msg.from, "MatchResults", &[Phase::Running, Phase::Paused])
+                    || !self.valid_arena(arena)
+                {
+                    return self.push_dashboard();
+                }
+                self.claim_underway(arena as usize, &winner, &loser, &msg.from);
+                if let Err(e) = check_result(
+                    self.arena_to_match[arena as usize].as_ref(),
+                    arena,
+                    &self.sender(&msg.from),
+                    &winner,
+                    &loser,
+                ) {
+                    self.reject(
+                        &msg.from,
+                        format!("rejected result for arena {}: {}", arena, e),
+                    );
+                    return self.push_dashboard();
+                }
+                self.emit(SpectatorEvent::MatchResult {
+                    arena,
+                    winner: self.event_player(&winner),
+                    loser: self.event_player(&loser),
+                });
+                let assigned = self.arena_to_match[arena as usize]
+                    .as_ref()
+                    .and_then(|m| m.match_id);
+                let margin = self.arena_to_match[arena as usize]
+                    .as_ref()
+                    .filter(|m| m.contains(&winner) && m.contains(&loser))
+                    .map(|m| (m.p1_score - m.p2_score).abs())
+                    .filter(|&margin| margin > 0);
+                if self.phase == Phase::Paused {
+                    self.held.push(HeldResult {
+                        arena,
+                        match_id: assigned,
+                        winner,
+                        loser,
+                        margin,
+                    });
+                } else {
+                    self.report_result(arena, assigned, winner, loser, margin);
+                }
+                if let Some(m) = self.arena_to_match[arena as usize].take() {
+                    if m.match_id.is_some() {
+                        self.played_for(Utc::now() - m.assigned_at);
+                    }
+                }
+                self.finished_at.insert(winner, Utc::now());
+                self.finished_at.insert(loser, Utc::now());
+                self.last_arena.insert(winner, arena as usize);
+                if self.phase == Phase::Running {
+                    self.send_pending_matches();
+                    self.finish_if_complete();
+                }
+            }
+            MessagePayload::MatchBegan { p1_id, p2_id } => {
+                let arena = self.arena_to_match.iter().position(|m| {
+                    m.as_ref()
+                        .is_some_and(|m| m.contains(&p1_id) && m.contains(&p2_id))
+                });
+                if let Some(arena) = arena {
+                    self.claim_arena(arena, &msg.from);
+                }
+                self.emit(SpectatorEvent::MatchBegan {
+                    arena: arena.map(|a| a as i32),
+                    p1: self.event_player(&p1_id),
+                    p2: self.event_player(&p2_id),
+                });
             }
-            MessagePayload::MatchBegan { p1Id, p2Id } => {}
             MessagePayload::UsersInServer { players } => {
+                if !self.require_phase(
+                    &msg.from,
+                    "UsersInServer",
+                    &[Phase::CollectingRoster, Phase::CheckingIn],
+                ) {
+                    return self.push_dashboard();
+                }
                 println!("recieved players {:?}", players);
-                self.players = players;
-                for player in &self.players {
-                    println!("adding player {:?}", player.name);
-                    crate::challonge::add_participant(&self.tc, &player.name, &player.steamId);
+                // pick up anyone added on the challonge site so they aren't added twice
+                self.refresh_entrants();
+                for player in players {
+                    if let Err(e) = self.register(&player.steam_id, &player.name) {
+                        self.record_error(format!(
+                            "could not add {} to the bracket: {}",
+                            player.name, e
+                        ));
+                        continue;
+                    }
+                    if !self.players.iter().any(|p| p.steam_id == player.steam_id) {
+                        self.players.push(player);
+                    }
                 }
-
-                crate::challonge::start_tournament(&self.tc);
-                self.send_pending_matches();
             }
             MessagePayload::Error { message } => {
-                println!("recieved error {:?}", message);
+                self.record_error(message);
+            }
+            MessagePayload::DisputeResult { match_id, reason } => {
+                if !self.require_admin(&msg.from, "DisputeResult") {
+                    return self.push_dashboard();
+                }
+                match self.results.iter_mut().find(|r| r.match_id == match_id) {
+                    Some(result) => result.disputed = Some(reason),
+                    None => self.record_error(format!("no reported result for match {}", match_id)),
+                }
+            }
+            MessagePayload::RetryMatch { match_id } => {
+                if !self.require_admin(&msg.from, "RetryMatch") {
+                    return self.push_dashboard();
+                }
+                self.cancelled.retain(|c| c.match_id != match_id);
+                if self.phase == Phase::Running {
+                    self.send_pending_matches();
+                }
+            }
+            MessagePayload::UndoLastResult {} => {
+                if !self.require_admin(&msg.from, "UndoLastResult")
+                    || !self.require_phase(
+                        &msg.from,
+                        "UndoLastResult",
+                        &[Phase::Running, Phase::Paused],
+                    )
+                {
+                    return self.push_dashboard();
+                }
+                if let Err(e) = self.undo_last_result() {
+                    self.reject(&msg.from, e);
+                }
+            }
+            MessagePayload::ReopenMatch { match_id } => {
+                if !self.require_admin(&msg.from, "ReopenMatch")
+                    || !self.require_phase(&msg.from, "ReopenMatch", &[Phase::Running])
+                {
+                    return self.push_dashboard();
+                }
+                if let Err(e) = self.reopen(match_id) {
+                    self.reject(&msg.from, e);
+                }
+                self.send_pending_matches();
+            }
+            MessagePayload::CorrectResult { match_id, winner } => {
+                if !self.require_admin(&msg.from, "CorrectResult")
+                    || !self.require_phase(&msg.from, "CorrectResult", &[Phase::Running])
+                {
+                    return self.push_dashboard();
+                }
+                if let Err(e) = self.correct_result(match_id, winner) {
+                    self.record_error(e);
+                }
+                self.send_pending_matches();
+            }
+            MessagePayload::OpenCheckIn {} => {
+                if !self.require_admin(&msg.from, "OpenCheckIn")
+                    || !self.require_phase(&msg.from, "OpenCheckIn", &[Phase::CollectingRoster])
+                {
+                    return self.push_dashboard();
+                }
+                let start_at = Utc::now() + TimeDelta::minutes(CHECK_IN_DURATION as i64);
+                if let Err(e) = self.open_check_in(start_at) {
+                    self.reject(&msg.from, e);
+                }
+            }
+            MessagePayload::CheckIn { steam_id, name } => {
+                // only the game server can vouch for who typed the command
+                if self.server_num(&msg.from).is_none() {
+                    self.reject(
+                        &msg.from,
+                        "CheckIn is only accepted from game servers".into(),
+                    );
+                    return self.push_dashboard();
+                }
+                match self.check_in(steam_id, name) {
+                    Ok(()) => msg.from.do_send(ForwardMessage {
+                        message: MessagePayload::CheckInConfirmed { steam_id },
+                        from: msg.from.clone(),
+                    }),
+                    Err(e) => self.reject(&msg.from, e),
+                }
+            }
+            MessagePayload::LockRoster {} => {
+                if !self.require_admin(&msg.from, "LockRoster")
+                    || !self.require_phase(
+                        &msg.from,
+                        "LockRoster",
+                        &[Phase::CollectingRoster, Phase::CheckingIn],
+                    )
+                {
+                    return self.push_dashboard();
+                }
+                if let Err(e) = self.lock_roster() {
+                    self.reject(&msg.from, e);
+                }
+            }
+            MessagePayload::StartBracket {} => {
+                if !self.require_admin(&msg.from, "StartBracket")
+                    || !self.require_phase(&msg.from, "StartBracket", &[Phase::RosterLocked])
+                {
+                    return self.push_dashboard();
+                }
+                if let Err(e) = self.start_bracket() {
+                    self.reject(&msg.from, e);
+                }
+            }
+            MessagePayload::PauseTournament {} => {
+                if !self.require_admin(&msg.from, "PauseTournament")
+                    || !self.require_phase(&msg.from, "PauseTournament", &[Phase::Running])
+                {
+                    return self.push_dashboard();
+                }
+                self.phase = Phase::Paused;
+                self.emit(SpectatorEvent::TournamentPaused {
+                    tournament: self.tc.name.clone(),
+                });
+                self.broadcast(MessagePayload::PauseTournament {});
+            }
+            MessagePayload::ResumeTournament {} => {
+                if !self.require_admin(&msg.from, "ResumeTournament")
+                    || !self.require_phase(&msg.from, "ResumeTournament", &[Phase::Paused])
+                {
+                    return self.push_dashboard();
+                }
+                self.phase = Phase::Running;
+                self.report_held();
+                self.emit(SpectatorEvent::TournamentResumed {
+                    tournament: self.tc.name.clone(),
+                });
+                self.broadcast(MessagePayload::ResumeTournament {});
+                self.send_pending_matches();
+                self.finish_if_complete();
+            }
+            MessagePayload::FinishTournament {} => {
+                if !self.require_admin(&msg.from, "FinishTournament")
+                    || !self.require_phase(&msg.from, "FinishTournament", &[Phase::Running])
+                {
+                    return self.push_dashboard();
+                }
+                if let Err(e) = self.finish() {
+                    self.reject(&msg.from, e);
+                }
+            }
+            MessagePayload::CreateTournament { options } => {
+                if !self.require_admin(&msg.from, "CreateTournament")
+                    || !self.require_phase(
+                        &msg.from,
+                        "CreateTournament",
+                        &[Phase::CollectingRoster, Phase::Finished],
+                    )
+                {
+                    return self.push_dashboard();
+                }
+                match crate::challonge::create_tournament(&self.c, &options) {
+                    Ok(tc) => self.switch_to(tc),
+                    Err(e) => self.reject(&msg.from, format!("could not create tournament: {}", e)),
+                }
+            }
+            MessagePayload::SaveTemplate {
+                name,
+                options,
+                schedule,
+            } => {
+                if !self.require_admin(&msg.from, "SaveTemplate") {
+                    return self.push_dashboard();
+                }
+                if let Some(Err(e)) = schedule.as_ref().map(|s| s.check(&options)) {
+                    self.reject(&msg.from, e);
+                    return self.push_dashboard();
+                }
+                self.templates.upsert(Template {
+                    name,
+                    options,
+                    schedule,
+                });
+            }
+            MessagePayload::DeleteTemplate { name } => {
+                if !self.require_admin(&msg.from, "DeleteTemplate") {
+                    return self.push_dashboard();
+                }
+                if !self.templates.remove(&name) {
+                    self.record_error(format!("no template called {}", name));
+                }
+            }
+            MessagePayload::SetRating { steam_id, rating } => {
+                if !self.require_admin(&msg.from, "SetRating") {
+                    return self.push_dashboard();
+                }
+                let name = self.display_name(&steam_id);
+                self.ratings.set_override(&steam_id, &name, rating);
+                // the bracket only takes new seeds until it starts
+                if self.phase == Phase::RosterLocked {
+                    self.apply_seeding();
+                }
+            }
+            MessagePayload::SetMinRest { seconds } => {
+                if !self.require_admin(&msg.from, "SetMinRest") {
+                    return self.push_dashboard();
+                }
+                self.min_rest = TimeDelta::seconds(seconds.max(0));
+                if self.phase == Phase::Running {
+                    self.dispatch_pending();
+                }
+            }
+            MessagePayload::SkipRest { steam_id } => {
+                if !self.require_admin(&msg.from, "SkipRest") {
+                    return self.push_dashboard();
+                }
+                self.finished_at.remove(&steam_id);
+                if self.phase == Phase::Running {
+                    self.dispatch_pending();
+                }
+            }
+            MessagePayload::SetArenaSelection { selection, round } => {
+                if !self.require_admin(&msg.from, "SetArenaSelection") {
+                    return self.push_dashboard();
+                }
+                match (selection.as_deref().map(crate::arenas::selection), round) {
+                    (Some(None), _) => self.reject(
+                        &msg.from,
+                        format!("unknown arena selection {}", selection.unwrap()),
+                    ),
+                    (Some(Some(s)), Some(round)) => {
+                        self.round_arena_selection.insert(round, s);
+                    }
+                    (Some(Some(s)), None) => self.arena_selection = s,
+                    (None, Some(round)) => {
+                        self.round_arena_selection.remove(&round);
+                    }
+                    (None, None) => self.reject(
+                        &msg.from,
+                        "SetArenaSelection needs a selection or a round".to_string(),
+                    ),
+                }
+            }
+            MessagePayload::FeatureMatch { match_id } => {
+                if !self.require_admin(&msg.from, "FeatureMatch") {
+                    return self.push_dashboard();
+                }
+                if let Some(id) = match_id {
+                    if !self
+                        .bracket
+                        .matches
+                        .iter()
+                        .any(|m| m.id == id && m.state == "open")
+                    {
+                        self.reject(&msg.from, format!("match {} isn't open", id));
+                        return self.push_dashboard();
+                    }
+                }
+                self.featured = match_id;
+                self.featured_pinned = match_id.is_some();
+                if self.phase == Phase::Running {
+                    self.dispatch_pending();
+                }
+            }
+            MessagePayload::SetDispatchPolicy { policy } => {
+                if !self.require_admin(&msg.from, "SetDispatchPolicy") {
+                    return self.push_dashboard();
+                }
+                match crate::dispatch::policy(&policy) {
+                    Some(policy) => {
+                        self.dispatch = policy;
+                        if self.phase == Phase::Running {
+                            self.send_pending_matches();
+                        }
+                    }
+                    None => self.reject(&msg.from, format!("unknown dispatch policy {}", policy)),
+                }
+            }
+            MessagePayload::CheckInConfirmed { .. } => {
+                self.record_error("CheckInConfirmed is only sent to game servers".to_string());
+            }
+            MessagePayload::Dashboard { .. } => {
+                self.record_error("Dashboard is only sent to admins".to_string());
             }
         }
+        self.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challonge::testing::steam_id;
+
+    fn arena_match(server: Option<&str>) -> ArenaMatch {
+        let mut m = ArenaMatch::new(Some(7), steam_id(1), steam_id(2));
+        m.server = server.map(str::to_string);
+        m
+    }
+
+    fn server(num: &str) -> Sender {
+        Sender::Server(num.to_string())
+    }
+
+    #[test]
+    fn result_has_to_name_the_players() {
+        let m = arena_match(None);
+        let check = |w, l| check_result(Some(&m), 3, &Sender::Admin, &steam_id(w), &steam_id(l));
+        assert!(check(1, 2).is_ok());
+        assert!(check(2, 1).is_ok());
+        assert!(check(1, 3).is_err());
+        assert!(check(1, 1).is_err());
+        assert!(check_result(None, 3, &Sender::Admin, &steam_id(1), &steam_id(2)).is_err());
+    }
+
+    #[test]
+    fn result_has_to_come_from_the_arenas_server() {
+        let m = arena_match(Some("1"));
+        let check = |sender: &Sender| check_result(Some(&m), 3, sender, &steam_id(1), &steam_id(2));
+        assert!(check(&server("1")).is_ok());
+        assert!(check(&server("2")).is_err());
+        assert!(check(&Sender::Unknown).is_err());
+        // admins can report any arena
+        assert!(check(&Sender::Admin).is_ok());
+        // nobody has claimed it yet, any game server will do
+        let m = arena_match(None);
+        assert!(check_result(Some(&m), 3, &server("2"), &steam_id(1), &steam_id(2)).is_ok());
+    }
+
+    #[test]
+    fn underway_match_is_claimed() {
+        let underway = vec![
+            PendingMatch {
+                match_id: 4,
+                p1_id: steam_id(3),
+                p2_id: steam_id(4),
+            },
+            PendingMatch {
+                match_id: 5,
+                p1_id: steam_id(1),
+                p2_id: steam_id(2),
+            },
+        ];
+        let claim = |m: Option<&ArenaMatch>, sender: &Sender, w, l| {
+            underway_claim(m, &underway, sender, &steam_id(w), &steam_id(l))
+        };
+        assert_eq!(claim(None, &server("1"), 2, 1), Some(1));
+        assert_eq!(claim(None, &Sender::Admin, 3, 4), Some(0));
+        assert_eq!(claim(None, &Sender::Unknown, 2, 1), None);
+        assert_eq!(claim(None, &server("1"), 1, 3), None);
+        assert_eq!(claim(None, &server("1"), 1, 1), None);
+        // the arena is already playing something
+        assert_eq!(claim(Some(&arena_match(None)), &server("1"), 2, 1), None);
+    }
+
+    #[test]
+    fn rest_counts_down_from_the_last_match() {
+        let now = Utc::now();
+        let rest = TimeDelta::seconds(90);
+        assert_eq!(rest_left(None, rest, now), TimeDelta::zero());
+        let finished = now - TimeDelta::seconds(30);
+        assert_eq!(rest_left(Some(finished), rest, now), TimeDelta::seconds(60));
+        let finished = now - TimeDelta::seconds(120);
+        assert_eq!(rest_left(Some(finished), rest, now), TimeDelta::zero());
+    }
+
+    #[test]
+    fn queue_waits_for_the_soonest_arena() {
+        assert_eq!(queue_waits(vec![3.0, 0.0], 8.0, 3), vec![0.0, 3.0, 8.0]);
+        assert_eq!(queue_waits(vec![], 8.0, 2), vec![0.0, 8.0]);
+        assert!(queue_waits(vec![1.0], 8.0, 0).is_empty());
     }
 }
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+
+use crate::challonge::Match;
+use crate::dispatch::{self, DispatchPolicy};
+use crate::server::DEFAULT_MATCH_MINUTES;
+
+// Replays a finished bracket with a given number of arenas to see how long each dispatch
+// policy would have taken. Run as `rustmge simulate <matches.json> [arenas]`, where
+// matches.json is what challonge's matches endpoint returns for the tournament.
+
+const DEFAULT_ARENAS: usize = 8;
+
+/// How long a recorded match took, from being marked underway to completed.
+fn minutes(m: &Match) -> f64 {
+    let parse = |t: &Option<String>| t.as_deref()?.parse::<DateTime<Utc>>().ok();
+    match (parse(&m.underway_at), parse(&m.completed_at)) {
+        (Some(start), Some(end)) if end > start => (end - start).num_seconds() as f64 / 60.0,
+        _ => DEFAULT_MATCH_MINUTES,
+    }
+}
+
+/// Minutes from the first match to the last one finishing.
+pub fn simulate(matches: &[Match], arenas: usize, policy: &dyn DispatchPolicy) -> f64 {
+    let played: Vec<&Match> = matches.iter().filter(|m| m.winner_id.is_some()).collect();
+    let is_played = |id: u64| played.iter().any(|m| m.id == id);
+    let mut done: Vec<u64> = vec![];
+    // (finishes at, match)
+    let mut running: Vec<(f64, &Match)> = vec![];
+    // in the order they became ready, which is what fifo goes by
+    let mut ready: Vec<&Match> = vec![];
+    let mut now = 0.0;
+
+    while done.len() < played.len() {
+        for &m in &played {
+            let started = done.contains(&m.id)
+                || running.iter().any(|(_, r)| r.id == m.id)
+                || ready.iter().any(|r| r.id == m.id);
+            let waiting_on = [m.player1_prereq_match_id, m.player2_prereq_match_id]
+                .into_iter()
+                .flatten()
+                .any(|prereq| is_played(prereq) && !done.contains(&prereq));
+            if !started && !waiting_on {
+                ready.push(m);
+            }
+        }
+
+        dispatch::sort(policy, matches, &mut ready, |m| m.id);
+        let mut i = 0;
+        while running.len() < arenas && i < ready.len() {
+            let m = ready[i];
+            // round robin and swiss have no prerequisites, players still can't be in two places
+            let busy = running.iter().any(|(_, r)| {
+                [r.player1_id, r.player2_id]
+                    .into_iter()
+                    .flatten()
+                    .any(|p| m.player1_id == Some(p) || m.player2_id == Some(p))
+            });
+            if busy {
+                i += 1;
+                continue;
+            }
+            running.push((now + minutes(m), ready.remove(i)));
+        }
+
+        let Some(next) = running.iter().map(|(end, _)| *end).min_by(f64::total_cmp) else {
+            println!("nothing left that can be played, stopping");
+            break;
+        };
+        now = next;
+        running.retain(|(end, m)| {
+            if *end <= now {
+                done.push(m.id);
+                false
+            } else {
+                true
+            }
+        });
+    }
+    now
+}
+
+pub fn run(args: &[String]) {
+    let Some(path) = args.first() else {
+        println!("usage: rustmge simulate <matches.json> [arenas]");
+        return;
+    };
+    let arenas = args
+        .get(1)
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(DEFAULT_ARENAS);
+    let matches = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| crate::challonge::parse_matches(&text).map_err(|e| e.to_string()))
+    {
+        Ok(matches) => matches,
+        Err(e) => {
+            println!("could not read {}: {}", path, e);
+            return;
+        }
+    };
+
+    println!("{} matches on {} arenas", matches.len(), arenas);
+    for policy in dispatch::policies() {
+        let minutes = simulate(&matches, arenas, policy.as_ref());
+        println!(
+            "{:>16}: {}h{:02}m",
+            policy.name(),
+            minutes as u64 / 60,
+            minutes as u64 % 60
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::CriticalPath;
+
+    // four players, single elimination, nothing timed so every match takes the default
+    fn single_elim() -> Vec<Match> {
+        vec![
+            Match::between(1, 1, 10, 40, Some(10)),
+            Match::between(2, 1, 20, 30, Some(20)),
+            Match::between(3, 2, 10, 20, Some(10)).after(1, 2),
+        ]
+    }
+
+    #[test]
+    fn one_arena_plays_everything_in_turn() {
+        let minutes = simulate(&single_elim(), 1, &CriticalPath);
+        assert_eq!(minutes, 3.0 * DEFAULT_MATCH_MINUTES);
+    }
+
+    #[test]
+    fn two_arenas_play_the_first_round_together() {
+        let minutes = simulate(&single_elim(), 2, &CriticalPath);
+        assert_eq!(minutes, 2.0 * DEFAULT_MATCH_MINUTES);
+    }
+
+    #[test]
+    fn a_player_is_only_in_one_arena() {
+        // round robin style, no prerequisites but player 10 is in both
+        let matches = vec![
+            Match::between(1, 1, 10, 20, Some(10)),
+            Match::between(2, 1, 10, 30, Some(30)),
+        ];
+        let minutes = simulate(&matches, 2, &CriticalPath);
+        assert_eq!(minutes, 2.0 * DEFAULT_MATCH_MINUTES);
+    }
+
+    #[test]
+    fn recorded_times_are_used() {
+        let m = Match {
+            underway_at: Some("2024-01-01T20:00:00Z".to_string()),
+            completed_at: Some("2024-01-01T20:12:30Z".to_string()),
+            ..Match::between(1, 1, 10, 20, Some(10))
+        };
+        assert_eq!(minutes(&m), 12.5);
+        assert_eq!(simulate(&[m], 1, &CriticalPath), 12.5);
+    }
+
+    #[test]
+    fn unplayed_matches_are_left_out() {
+        let mut matches = single_elim();
+        matches.push(Match::between(4, 2, 40, 30, None).after(1, 2));
+        assert_eq!(
+            simulate(&matches, 1, &CriticalPath),
+            3.0 * DEFAULT_MATCH_MINUTES
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// SteamID64 of account 0, individual account in the public universe
+const STEAMID64_BASE: u64 = 76561197960265728;
+const MAX_ACCOUNT_ID: u64 = u32::MAX as u64;
+
+/// A player's steamid, always stored as SteamID64.
+///
+/// Accepts `STEAM_X:Y:Z`, `[U:1:N]` and SteamID64, so the same player compares equal
+/// whatever the plugin or challonge handed us. Bots and the engine's placeholders like
+/// `STEAM_ID_PENDING` don't parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SteamId(u64);
+
+impl SteamId {
+    fn from_account_id(account_id: u64) -> Result<Self, String> {
+        if account_id == 0 || account_id > MAX_ACCOUNT_ID {
+            return Err(format!("account id {} is out of range", account_id));
+        }
+        Ok(SteamId(STEAMID64_BASE + account_id))
+    }
+}
+
+impl FromStr for SteamId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("{:?} is not a steamid", s);
+
+        if s.eq_ignore_ascii_case("BOT") || s.starts_with("STEAM_ID_") {
+            return Err(format!("{:?} is a bot or an unauthenticated player", s));
+        }
+
+        // STEAM_X:Y:Z, X is the universe and differs between games
+        if let Some(rest) = s.strip_prefix("STEAM_") {
+            let parts: Vec<&str> = rest.split(':').collect();
+            let [_, y, z] = parts[..] else {
+                return Err(invalid());
+            };
+            let y: u64 = y.parse().map_err(|_| invalid())?;
+            let z: u64 = z.parse().map_err(|_| invalid())?;
+            if y > 1 {
+                return Err(invalid());
+            }
+            return SteamId::from_account_id(z * 2 + y);
+        }
+
+        // [U:1:N], brackets optional
+        let bare = s
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .unwrap_or(s);
+        if let Some(n) = bare.strip_prefix("U:1:") {
+            return SteamId::from_account_id(n.parse().map_err(|_| invalid())?);
+        }
+
+        let id: u64 = s.parse().map_err(|_| invalid())?;
+        if id <= STEAMID64_BASE {
+            return Err(invalid());
+        }
+        SteamId::from_account_id(id - STEAMID64_BASE)
+    }
+}
+
+impl TryFrom<String> for SteamId {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SteamId> for String {
+    fn from(id: SteamId) -> Self {
+        id.to_string()
+    }
+}
+
+impl fmt::Display for SteamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// For lists the plugin builds from whoever is on the server, where one bot shouldn't
+/// throw away the whole message.
+pub fn skip_invalid<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .filter_map(|v| match serde_json::from_value(v.clone()) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                println!("skipping {}: {}", v, e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// An empty string means nobody, both ways, which is what the plugin sends.
+pub fn optional<'de, D>(deserializer: D) -> Result<Option<SteamId>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(s) if s.is_empty() => Ok(None),
+        Some(s) => s.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+pub fn serialize_optional<S>(id: &Option<SteamId>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match id {
+        Some(id) => serializer.collect_str(id),
+        None => serializer.serialize_str(""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT_9: u64 = 76561197960265737;
+
+    fn parse(s: &str) -> Result<SteamId, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn steam_x_y_z() {
+        assert_eq!(parse("STEAM_0:1:4"), Ok(SteamId(ACCOUNT_9)));
+        // the universe digit differs between games
+        assert_eq!(parse("STEAM_1:1:4"), Ok(SteamId(ACCOUNT_9)));
+        assert!(parse("STEAM_0:2:4").is_err());
+        assert!(parse("STEAM_0:1").is_err());
+        assert!(parse("STEAM_0:1:x").is_err());
+    }
+
+    #[test]
+    fn steam3() {
+        assert_eq!(parse("[U:1:9]"), Ok(SteamId(ACCOUNT_9)));
+        assert_eq!(parse("U:1:9"), Ok(SteamId(ACCOUNT_9)));
+        assert!(parse("[U:1:]").is_err());
+    }
+
+    #[test]
+    fn steamid64() {
+        assert_eq!(parse("76561197960265737"), Ok(SteamId(ACCOUNT_9)));
+        assert_eq!(parse(" 76561197960265737 "), Ok(SteamId(ACCOUNT_9)));
+    }
+
+    #[test]
+    fn all_formats_agree() {
+        let ids: Vec<_> = ["STEAM_0:1:4", "[U:1:9]", "76561197960265737"]
+            .into_iter()
+            .map(parse)
+            .collect();
+        assert!(ids.windows(2).all(|w| w[0] == w[1]));
+    }
+
+    #[test]
+    fn bots_and_placeholders() {
+        assert!(parse("BOT").is_err());
+        assert!(parse("bot").is_err());
+        assert!(parse("STEAM_ID_PENDING").is_err());
+        assert!(parse("STEAM_ID_LAN").is_err());
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert!(parse("[U:1:0]").is_err());
+        assert!(parse("STEAM_0:0:0").is_err());
+        assert!(parse(&format!("[U:1:{}]", MAX_ACCOUNT_ID + 1)).is_err());
+        assert!(parse(&STEAMID64_BASE.to_string()).is_err());
+        assert!(parse(&(STEAMID64_BASE + MAX_ACCOUNT_ID + 1).to_string()).is_err());
+        assert!(parse("1").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn displays_as_steamid64() {
+        assert_eq!(
+            parse("STEAM_0:1:4").unwrap().to_string(),
+            ACCOUNT_9.to_string()
+        );
+    }
+
+    #[derive(Deserialize)]
+    struct Players {
+        #[serde(deserialize_with = "skip_invalid")]
+        players: Vec<SteamId>,
+    }
+
+    #[test]
+    fn skip_invalid_keeps_the_rest() {
+        let players: Players =
+            serde_json::from_str(r#"{"players": ["BOT", "[U:1:9]", "1", 5]}"#).unwrap();
+        assert_eq!(players.players, vec![SteamId(ACCOUNT_9)]);
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::challonge::{Format, GrandFinals, Ranking, TournamentOptions};
+use crate::scheduler::Schedule;
+
+pub const TEMPLATES_FILE: &str = "templates.json";
+
+/// Settings for one of our recurring formats. The admin page fills the create form from
+/// these, name and url usually get changed before creating.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Template {
+    pub name: String,
+    pub options: TournamentOptions,
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TemplateStore {
+    templates: Vec<Template>,
+    #[serde(skip)]
+    path: String,
+}
+
+fn builtin() -> Vec<Template> {
+    vec![
+        Template {
+            name: "weekly cup".to_string(),
+            options: TournamentOptions {
+                name: "mge.tf weekly cup".to_string(),
+                url: "mge_weekly".to_string(),
+                description: String::new(),
+                format: Format::DoubleElimination,
+                hold_third_place_match: false,
+                grand_finals: GrandFinals::TwoChances,
+                ranked_by: Ranking::MatchWins,
+                signup_cap: 64,
+                swiss_rounds: 0,
+                start_at: None,
+                private: false,
+            },
+            schedule: None,
+        },
+        Template {
+            name: "quick bracket".to_string(),
+            options: TournamentOptions {
+                name: "mge.tf quick bracket".to_string(),
+                url: "mge_quick".to_string(),
+                description: String::new(),
+                format: Format::SingleElimination,
+                hold_third_place_match: true,
+                grand_finals: GrandFinals::SingleMatch,
+                ranked_by: Ranking::MatchWins,
+                signup_cap: 32,
+                swiss_rounds: 0,
+                start_at: None,
+                private: false,
+            },
+            schedule: None,
+        },
+    ]
+}
+
+impl TemplateStore {
+    pub fn load(path: &str) -> Self {
+        let mut store: TemplateStore = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                println!(
+                    "could not parse {}: {}, using the built in templates",
+                    path, e
+                );
+                TemplateStore::default()
+            }),
+            Err(_) => TemplateStore::default(),
+        };
+        if store.templates.is_empty() {
+            store.templates = builtin();
+        }
+        store.path = path.to_string();
+        store
+    }
+
+    pub fn save(&self) {
+        let text = serde_json::to_string_pretty(self).unwrap();
+        if let Err(e) = std::fs::write(&self.path, text) {
+            println!("could not write {}: {}", self.path, e);
+        }
+    }
+
+    pub fn all(&self) -> &[Template] {
+        &self.templates
+    }
+
+    pub fn all_mut(&mut self) -> &mut [Template] {
+        &mut self.templates
+    }
+
+    /// Adds the template, replacing one with the same name. A scheduled instance that
+    /// hasn't started yet is kept.
+    pub fn upsert(&mut self, mut template: Template) {
+        match self.templates.iter_mut().find(|t| t.name == template.name) {
+            Some(existing) => {
+                let current = existing.schedule.as_mut().and_then(|s| s.current.take());
+                if let Some(schedule) = &mut template.schedule {
+                    schedule.current = schedule.current.take().or(current);
+                }
+                *existing = template;
+            }
+            None => self.templates.push(template),
+        }
+        self.save();
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.templates.len();
+        self.templates.retain(|t| t.name != name);
+        if self.templates.len() == before {
+            return false;
+        }
+        self.save();
+        true
+    }
+}